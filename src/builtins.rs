@@ -1,7 +1,19 @@
-use std::rc::Rc;
+use std::{cell::RefCell, cmp::Ordering, collections::HashMap, fmt::Write, rc::Rc};
+
+use im_rc::{OrdMap, OrdSet};
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
-    BadProgram, Function, Result, Scope, UserFn, Value, eval_block, eval_do_block, io::Io,
+    BadProgram, Function, Result, Scope, UserFn, Value, compare, csv, encoding, eval, eval_block,
+    eval_do_block,
+    io::Io,
+    json,
+    keyword::Keyword,
+    lazy::{Seq, Thunk},
+    list::List,
+    number::{self, Int, Rounding},
+    parser, time,
 };
 
 pub fn resolve(name: &str) -> Result<&'static Value> {
@@ -10,20 +22,198 @@ pub fn resolve(name: &str) -> Result<&'static Value> {
         "-" => &Value::Fn(Function::Builtin(BuiltinFn::Sub)),
         "*" => &Value::Fn(Function::Builtin(BuiltinFn::Mul)),
         "/" => &Value::Fn(Function::Builtin(BuiltinFn::Div)),
+        "quot" => &Value::Fn(Function::Builtin(BuiltinFn::Quot)),
+        "rem" => &Value::Fn(Function::Builtin(BuiltinFn::Rem)),
+        "float" => &Value::Fn(Function::Builtin(BuiltinFn::Float)),
+        "numerator" => &Value::Fn(Function::Builtin(BuiltinFn::Numerator)),
+        "denominator" => &Value::Fn(Function::Builtin(BuiltinFn::Denominator)),
+        "mod" => &Value::Fn(Function::Builtin(BuiltinFn::Mod)),
+        "pow" => &Value::Fn(Function::Builtin(BuiltinFn::Pow)),
+        "sqrt" => &Value::Fn(Function::Builtin(BuiltinFn::Sqrt)),
+        "abs" => &Value::Fn(Function::Builtin(BuiltinFn::Abs)),
+        "floor" => &Value::Fn(Function::Builtin(BuiltinFn::Floor)),
+        "ceil" => &Value::Fn(Function::Builtin(BuiltinFn::Ceil)),
+        "round" => &Value::Fn(Function::Builtin(BuiltinFn::Round)),
+        "truncate" => &Value::Fn(Function::Builtin(BuiltinFn::Truncate)),
+        "min" => &Value::Fn(Function::Builtin(BuiltinFn::Min)),
+        "max" => &Value::Fn(Function::Builtin(BuiltinFn::Max)),
+        "exp" => &Value::Fn(Function::Builtin(BuiltinFn::Exp)),
+        "ln" => &Value::Fn(Function::Builtin(BuiltinFn::Ln)),
+        "log" => &Value::Fn(Function::Builtin(BuiltinFn::Log)),
+        "sin" => &Value::Fn(Function::Builtin(BuiltinFn::Sin)),
+        "cos" => &Value::Fn(Function::Builtin(BuiltinFn::Cos)),
+        "tan" => &Value::Fn(Function::Builtin(BuiltinFn::Tan)),
+        "asin" => &Value::Fn(Function::Builtin(BuiltinFn::Asin)),
+        "acos" => &Value::Fn(Function::Builtin(BuiltinFn::Acos)),
+        "atan" => &Value::Fn(Function::Builtin(BuiltinFn::Atan)),
+        "atan2" => &Value::Fn(Function::Builtin(BuiltinFn::Atan2)),
+        "nan?" => &Value::Fn(Function::Builtin(BuiltinFn::IsNan)),
+        "infinite?" => &Value::Fn(Function::Builtin(BuiltinFn::IsInfinite)),
+        "finite?" => &Value::Fn(Function::Builtin(BuiltinFn::IsFinite)),
+        "pi" => &Value::Number(std::f64::consts::PI),
+        "e" => &Value::Number(std::f64::consts::E),
+        "infinity" => &Value::Number(f64::INFINITY),
+        "-infinity" => &Value::Number(f64::NEG_INFINITY),
+        "nan" => &Value::Number(f64::NAN),
+
+        "=" => &Value::Fn(Function::Builtin(BuiltinFn::Eq)),
+        "<" => &Value::Fn(Function::Builtin(BuiltinFn::Lt)),
+        ">" => &Value::Fn(Function::Builtin(BuiltinFn::Gt)),
+        "<=" => &Value::Fn(Function::Builtin(BuiltinFn::Le)),
+        ">=" => &Value::Fn(Function::Builtin(BuiltinFn::Ge)),
+
+        "true" => &Value::Bool(true),
+        "false" => &Value::Bool(false),
+        "nil" => &Value::Nil,
 
         "list" => &Value::Fn(Function::Builtin(BuiltinFn::List)),
         "map" => &Value::Fn(Function::Builtin(BuiltinFn::Map)),
+        "cons" => &Value::Fn(Function::Builtin(BuiltinFn::Cons)),
+        "append" => &Value::Fn(Function::Builtin(BuiltinFn::Append)),
+        "nth" => &Value::Fn(Function::Builtin(BuiltinFn::Nth)),
+        "first" => &Value::Fn(Function::Builtin(BuiltinFn::First)),
+        "rest" => &Value::Fn(Function::Builtin(BuiltinFn::Rest)),
+        "filter" => &Value::Fn(Function::Builtin(BuiltinFn::Filter)),
+        "fold" => &Value::Fn(Function::Builtin(BuiltinFn::Fold)),
+        "reduce" => &Value::Fn(Function::Builtin(BuiltinFn::Reduce)),
+        "reverse" => &Value::Fn(Function::Builtin(BuiltinFn::Reverse)),
+        "concat" => &Value::Fn(Function::Builtin(BuiltinFn::Concat)),
+        "sort" => &Value::Fn(Function::Builtin(BuiltinFn::Sort)),
+        "sort_by" => &Value::Fn(Function::Builtin(BuiltinFn::SortBy)),
+        "zip" => &Value::Fn(Function::Builtin(BuiltinFn::Zip)),
+        "flatten" => &Value::Fn(Function::Builtin(BuiltinFn::Flatten)),
+        "any" => &Value::Fn(Function::Builtin(BuiltinFn::Any)),
+        "all" => &Value::Fn(Function::Builtin(BuiltinFn::All)),
+        "find" => &Value::Fn(Function::Builtin(BuiltinFn::Find)),
+        "index_of" => &Value::Fn(Function::Builtin(BuiltinFn::IndexOf)),
+        "group_by" => &Value::Fn(Function::Builtin(BuiltinFn::GroupBy)),
+        "partition" => &Value::Fn(Function::Builtin(BuiltinFn::Partition)),
+        "unique" => &Value::Fn(Function::Builtin(BuiltinFn::Unique)),
+
+        "force" => &Value::Fn(Function::Builtin(BuiltinFn::Force)),
+        "lazy_cons" => &Value::Fn(Function::Builtin(BuiltinFn::LazyCons)),
+        "to_list" => &Value::Fn(Function::Builtin(BuiltinFn::ToList)),
+        "range" => &Value::Fn(Function::Builtin(BuiltinFn::Range)),
+        "repeat" => &Value::Fn(Function::Builtin(BuiltinFn::Repeat)),
+        "iterate" => &Value::Fn(Function::Builtin(BuiltinFn::Iterate)),
+        "take" => &Value::Fn(Function::Builtin(BuiltinFn::Take)),
+        "drop" => &Value::Fn(Function::Builtin(BuiltinFn::Drop)),
+        "take_while" => &Value::Fn(Function::Builtin(BuiltinFn::TakeWhile)),
+
+        "length" => &Value::Fn(Function::Builtin(BuiltinFn::Length)),
+        "byte_length" => &Value::Fn(Function::Builtin(BuiltinFn::ByteLength)),
+        "grapheme_length" => &Value::Fn(Function::Builtin(BuiltinFn::GraphemeLength)),
+        "char_at" => &Value::Fn(Function::Builtin(BuiltinFn::CharAt)),
+        "chars" => &Value::Fn(Function::Builtin(BuiltinFn::Chars)),
+        "graphemes" => &Value::Fn(Function::Builtin(BuiltinFn::Graphemes)),
+        "substring" => &Value::Fn(Function::Builtin(BuiltinFn::Substring)),
+        "split" => &Value::Fn(Function::Builtin(BuiltinFn::Split)),
+        "join" => &Value::Fn(Function::Builtin(BuiltinFn::Join)),
+        "lines" => &Value::Fn(Function::Builtin(BuiltinFn::Lines)),
+        "trim" => &Value::Fn(Function::Builtin(BuiltinFn::Trim)),
+        "upper" => &Value::Fn(Function::Builtin(BuiltinFn::Upper)),
+        "lower" => &Value::Fn(Function::Builtin(BuiltinFn::Lower)),
+        "starts_with" => &Value::Fn(Function::Builtin(BuiltinFn::StartsWith)),
+        "ends_with" => &Value::Fn(Function::Builtin(BuiltinFn::EndsWith)),
+        "replace" => &Value::Fn(Function::Builtin(BuiltinFn::Replace)),
+        "pad_left" => &Value::Fn(Function::Builtin(BuiltinFn::PadLeft)),
+        "pad_right" => &Value::Fn(Function::Builtin(BuiltinFn::PadRight)),
+
+        "regex" => &Value::Fn(Function::Builtin(BuiltinFn::Regex)),
+        "regex_match" => &Value::Fn(Function::Builtin(BuiltinFn::RegexMatch)),
+        "regex_find_all" => &Value::Fn(Function::Builtin(BuiltinFn::RegexFindAll)),
+        "regex_captures" => &Value::Fn(Function::Builtin(BuiltinFn::RegexCaptures)),
+        "regex_replace" => &Value::Fn(Function::Builtin(BuiltinFn::RegexReplace)),
+        "regex_split" => &Value::Fn(Function::Builtin(BuiltinFn::RegexSplit)),
+
+        "bytes_slice" => &Value::Fn(Function::Builtin(BuiltinFn::BytesSlice)),
+        "bytes_concat" => &Value::Fn(Function::Builtin(BuiltinFn::BytesConcat)),
+        "utf8_encode" => &Value::Fn(Function::Builtin(BuiltinFn::Utf8Encode)),
+        "utf8_decode" => &Value::Fn(Function::Builtin(BuiltinFn::Utf8Decode)),
+        "hex_encode" => &Value::Fn(Function::Builtin(BuiltinFn::HexEncode)),
+        "hex_decode" => &Value::Fn(Function::Builtin(BuiltinFn::HexDecode)),
+        "base64_encode" => &Value::Fn(Function::Builtin(BuiltinFn::Base64Encode)),
+        "base64_decode" => &Value::Fn(Function::Builtin(BuiltinFn::Base64Decode)),
+        "json_parse" => &Value::Fn(Function::Builtin(BuiltinFn::JsonParse)),
+        "json_stringify" => &Value::Fn(Function::Builtin(BuiltinFn::JsonStringify)),
+        "csv_parse" => &Value::Fn(Function::Builtin(BuiltinFn::CsvParse)),
+        "csv_format" => &Value::Fn(Function::Builtin(BuiltinFn::CsvFormat)),
+
+        "datetime" => &Value::Fn(Function::Builtin(BuiltinFn::DateTime)),
+        "datetime_parse" => &Value::Fn(Function::Builtin(BuiltinFn::DateTimeParse)),
+        "datetime_format" => &Value::Fn(Function::Builtin(BuiltinFn::DateTimeFormat)),
+        "datetime_fields" => &Value::Fn(Function::Builtin(BuiltinFn::DateTimeFields)),
+        "datetime_add" => &Value::Fn(Function::Builtin(BuiltinFn::DateTimeAdd)),
+        "datetime_diff" => &Value::Fn(Function::Builtin(BuiltinFn::DateTimeDiff)),
+        "datetime_to_offset" => &Value::Fn(Function::Builtin(BuiltinFn::DateTimeToOffset)),
+        "duration" => &Value::Fn(Function::Builtin(BuiltinFn::Duration)),
+        "duration_seconds" => &Value::Fn(Function::Builtin(BuiltinFn::DurationSeconds)),
+
+        "get" => &Value::Fn(Function::Builtin(BuiltinFn::Get)),
+        "assoc" => &Value::Fn(Function::Builtin(BuiltinFn::Assoc)),
+        "dissoc" => &Value::Fn(Function::Builtin(BuiltinFn::Dissoc)),
+        "keys" => &Value::Fn(Function::Builtin(BuiltinFn::Keys)),
+        "values" => &Value::Fn(Function::Builtin(BuiltinFn::Values)),
+        "merge" => &Value::Fn(Function::Builtin(BuiltinFn::Merge)),
+        "contains" => &Value::Fn(Function::Builtin(BuiltinFn::Contains)),
+
+        "union" => &Value::Fn(Function::Builtin(BuiltinFn::Union)),
+        "intersection" => &Value::Fn(Function::Builtin(BuiltinFn::Intersection)),
+        "difference" => &Value::Fn(Function::Builtin(BuiltinFn::Difference)),
+        "member" => &Value::Fn(Function::Builtin(BuiltinFn::Member)),
+        "insert" => &Value::Fn(Function::Builtin(BuiltinFn::Insert)),
+        "remove" => &Value::Fn(Function::Builtin(BuiltinFn::Remove)),
+
+        "partial" => &Value::Fn(Function::Builtin(BuiltinFn::Partial)),
+        "curry" => &Value::Fn(Function::Builtin(BuiltinFn::Curry)),
+        "compose" => &Value::Fn(Function::Builtin(BuiltinFn::Compose)),
+        "flip" => &Value::Fn(Function::Builtin(BuiltinFn::Flip)),
+        "arity" => &Value::Fn(Function::Builtin(BuiltinFn::Arity)),
+
+        "type_of" => &Value::Fn(Function::Builtin(BuiltinFn::TypeOf)),
+        "show" => &Value::Fn(Function::Builtin(BuiltinFn::Show)),
+        "read" => &Value::Fn(Function::Builtin(BuiltinFn::Read)),
+        "parse_number" => &Value::Fn(Function::Builtin(BuiltinFn::ParseNumber)),
+        "interpolate" => &Value::Fn(Function::Builtin(BuiltinFn::Interpolate)),
+        "number?" => &Value::Fn(Function::Builtin(BuiltinFn::IsNumber)),
+        "int?" => &Value::Fn(Function::Builtin(BuiltinFn::IsInt)),
+        "rational?" => &Value::Fn(Function::Builtin(BuiltinFn::IsRational)),
+        "float?" => &Value::Fn(Function::Builtin(BuiltinFn::IsFloat)),
+        "bool?" => &Value::Fn(Function::Builtin(BuiltinFn::IsBool)),
+        "string?" => &Value::Fn(Function::Builtin(BuiltinFn::IsString)),
+        "char?" => &Value::Fn(Function::Builtin(BuiltinFn::IsChar)),
+        "bytes?" => &Value::Fn(Function::Builtin(BuiltinFn::IsBytes)),
+        "symbol?" => &Value::Fn(Function::Builtin(BuiltinFn::IsSymbol)),
+        "keyword?" => &Value::Fn(Function::Builtin(BuiltinFn::IsKeyword)),
+        "list?" => &Value::Fn(Function::Builtin(BuiltinFn::IsList)),
+        "map?" => &Value::Fn(Function::Builtin(BuiltinFn::IsMap)),
+        "set?" => &Value::Fn(Function::Builtin(BuiltinFn::IsSet)),
+        "record?" => &Value::Fn(Function::Builtin(BuiltinFn::IsRecord)),
+        "variant?" => &Value::Fn(Function::Builtin(BuiltinFn::IsVariant)),
+        "regex?" => &Value::Fn(Function::Builtin(BuiltinFn::IsRegex)),
+        "datetime?" => &Value::Fn(Function::Builtin(BuiltinFn::IsDateTime)),
+        "duration?" => &Value::Fn(Function::Builtin(BuiltinFn::IsDuration)),
+        "fn?" => &Value::Fn(Function::Builtin(BuiltinFn::IsFn)),
+        "macro?" => &Value::Fn(Function::Builtin(BuiltinFn::IsMacro)),
+        "io?" => &Value::Fn(Function::Builtin(BuiltinFn::IsIo)),
+        "thunk?" => &Value::Fn(Function::Builtin(BuiltinFn::IsThunk)),
+        "seq?" => &Value::Fn(Function::Builtin(BuiltinFn::IsSeq)),
+        "nil?" => &Value::Fn(Function::Builtin(BuiltinFn::IsNil)),
 
         "then" => &Value::Fn(Function::Builtin(BuiltinFn::Then)),
         "bind" => &Value::Fn(Function::Builtin(BuiltinFn::Bind)),
         "return" => &Value::Fn(Function::Builtin(BuiltinFn::Return)),
 
         "read_line" => &Value::Fn(Function::Builtin(BuiltinFn::ReadLine)),
+        "now" => &Value::Fn(Function::Builtin(BuiltinFn::Now)),
         "print_line" => &Value::Fn(Function::Builtin(BuiltinFn::PrintLine)),
 
         "block" => &Value::Macro(BuiltinMacro::Block),
         "do" => &Value::Macro(BuiltinMacro::Do),
         "fn" => &Value::Macro(BuiltinMacro::Fn),
+        "if" => &Value::Macro(BuiltinMacro::If),
+        "case" => &Value::Macro(BuiltinMacro::Case),
+        "delay" => &Value::Macro(BuiltinMacro::Delay),
         _ => return Err(BadProgram),
     })
 }
@@ -34,15 +224,182 @@ pub enum BuiltinFn {
     Sub,
     Mul,
     Div,
+    Quot,
+    Rem,
+    Float,
+    Numerator,
+    Denominator,
+    Mod,
+    Pow,
+    Sqrt,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    Truncate,
+    Min,
+    Max,
+    Exp,
+    Ln,
+    Log,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    IsNan,
+    IsInfinite,
+    IsFinite,
+
+    Eq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
 
     List,
     Map,
+    Cons,
+    Append,
+    Nth,
+    First,
+    Rest,
+    Filter,
+    Fold,
+    Reduce,
+    Reverse,
+    Concat,
+    Sort,
+    SortBy,
+    Zip,
+    Flatten,
+    Any,
+    All,
+    Find,
+    IndexOf,
+    GroupBy,
+    Partition,
+    Unique,
+
+    Force,
+    LazyCons,
+    ToList,
+    Range,
+    Repeat,
+    Iterate,
+    Take,
+    Drop,
+    TakeWhile,
+
+    Length,
+    ByteLength,
+    GraphemeLength,
+    CharAt,
+    Chars,
+    Graphemes,
+    Substring,
+    Split,
+    Join,
+    Lines,
+    Trim,
+    Upper,
+    Lower,
+    StartsWith,
+    EndsWith,
+    Replace,
+    PadLeft,
+    PadRight,
+
+    Regex,
+    RegexMatch,
+    RegexFindAll,
+    RegexCaptures,
+    RegexReplace,
+    RegexSplit,
+
+    BytesSlice,
+    BytesConcat,
+    Utf8Encode,
+    Utf8Decode,
+    HexEncode,
+    HexDecode,
+    Base64Encode,
+    Base64Decode,
+    JsonParse,
+    JsonStringify,
+    CsvParse,
+    CsvFormat,
+
+    DateTime,
+    DateTimeParse,
+    DateTimeFormat,
+    DateTimeFields,
+    DateTimeAdd,
+    DateTimeDiff,
+    DateTimeToOffset,
+    Duration,
+    DurationSeconds,
+
+    Get,
+    Assoc,
+    Dissoc,
+    Keys,
+    Values,
+    Merge,
+    Contains,
+
+    Union,
+    Intersection,
+    Difference,
+    Member,
+    Insert,
+    Remove,
+
+    Partial,
+    Curry,
+    Compose,
+    Flip,
+    Arity,
+
+    TypeOf,
+    Show,
+    Read,
+    ParseNumber,
+    // What interpolated strings turn into. It can also be called as `interpolate`.
+    Interpolate,
+    IsNumber,
+    IsInt,
+    IsRational,
+    IsFloat,
+    IsBool,
+    IsString,
+    IsChar,
+    IsBytes,
+    IsSymbol,
+    IsKeyword,
+    IsList,
+    IsMap,
+    IsSet,
+    IsRecord,
+    IsVariant,
+    IsRegex,
+    IsDateTime,
+    IsDuration,
+    IsFn,
+    IsMacro,
+    IsIo,
+    IsThunk,
+    IsSeq,
+    IsNil,
 
     Then,
     Bind,
     Return,
 
     ReadLine,
+    Now,
     PrintLine,
 }
 
@@ -50,13 +407,177 @@ pub enum BuiltinFn {
 pub enum BuiltinMacro {
     Block,
     Fn,
+    If,
+    Case,
     Do,
+    Delay,
 }
 
 impl BuiltinFn {
-    pub fn call(self, mut params: impl ExactSizeIterator<Item = Result<Value>>) -> Result<Value> {
+    // `None` means the function is variadic.
+    pub fn arity(self) -> Option<usize> {
+        Some(match self {
+            BuiltinFn::Add | BuiltinFn::Mul => return None,
+            BuiltinFn::Sub | BuiltinFn::Div => 2,
+            BuiltinFn::Quot | BuiltinFn::Rem | BuiltinFn::Mod => 2,
+            BuiltinFn::Float | BuiltinFn::Numerator | BuiltinFn::Denominator => 1,
+            BuiltinFn::Pow => 2,
+            BuiltinFn::Sqrt | BuiltinFn::Abs => 1,
+            BuiltinFn::Floor | BuiltinFn::Ceil | BuiltinFn::Round | BuiltinFn::Truncate => 1,
+            BuiltinFn::Min | BuiltinFn::Max => return None,
+            BuiltinFn::Exp | BuiltinFn::Ln | BuiltinFn::Log => 1,
+            BuiltinFn::Sin | BuiltinFn::Cos | BuiltinFn::Tan => 1,
+            BuiltinFn::Asin | BuiltinFn::Acos | BuiltinFn::Atan => 1,
+            BuiltinFn::Atan2 => 2,
+            BuiltinFn::IsNan | BuiltinFn::IsInfinite | BuiltinFn::IsFinite => 1,
+
+            BuiltinFn::Eq | BuiltinFn::Lt | BuiltinFn::Gt | BuiltinFn::Le | BuiltinFn::Ge => 2,
+
+            BuiltinFn::List => return None,
+            BuiltinFn::Map => 2,
+            BuiltinFn::Cons | BuiltinFn::Append | BuiltinFn::Nth => 2,
+            BuiltinFn::First | BuiltinFn::Rest => 1,
+            BuiltinFn::Filter => 2,
+            BuiltinFn::Fold => 3,
+            BuiltinFn::Reduce => 2,
+            BuiltinFn::Reverse => 1,
+            BuiltinFn::Concat => return None,
+            BuiltinFn::Sort => 1,
+            BuiltinFn::SortBy | BuiltinFn::Zip => 2,
+            BuiltinFn::Flatten => 1,
+            BuiltinFn::Any | BuiltinFn::All | BuiltinFn::Find | BuiltinFn::IndexOf => 2,
+            BuiltinFn::GroupBy | BuiltinFn::Partition => 2,
+            BuiltinFn::Unique => 1,
+
+            BuiltinFn::Force => 1,
+            BuiltinFn::LazyCons => 2,
+            BuiltinFn::ToList => 1,
+            BuiltinFn::Range => 2,
+            BuiltinFn::Repeat => 2,
+            BuiltinFn::Iterate => 2,
+            BuiltinFn::Take | BuiltinFn::Drop | BuiltinFn::TakeWhile => 2,
+
+            BuiltinFn::Length | BuiltinFn::ByteLength | BuiltinFn::GraphemeLength => 1,
+            BuiltinFn::CharAt => 2,
+            BuiltinFn::Chars | BuiltinFn::Graphemes => 1,
+            BuiltinFn::Substring => 3,
+            BuiltinFn::Split | BuiltinFn::Join => 2,
+            BuiltinFn::Lines | BuiltinFn::Trim | BuiltinFn::Upper | BuiltinFn::Lower => 1,
+            BuiltinFn::StartsWith | BuiltinFn::EndsWith => 2,
+            BuiltinFn::Replace => 3,
+            BuiltinFn::PadLeft | BuiltinFn::PadRight => 2,
+
+            BuiltinFn::Regex => 1,
+            BuiltinFn::RegexMatch | BuiltinFn::RegexFindAll | BuiltinFn::RegexCaptures => 2,
+            BuiltinFn::RegexReplace => 3,
+            BuiltinFn::RegexSplit => 2,
+
+            BuiltinFn::BytesSlice => 3,
+            BuiltinFn::BytesConcat => return None,
+            BuiltinFn::Utf8Encode | BuiltinFn::Utf8Decode => 1,
+            BuiltinFn::HexEncode | BuiltinFn::HexDecode => 1,
+            BuiltinFn::Base64Encode | BuiltinFn::Base64Decode => 1,
+            BuiltinFn::JsonParse | BuiltinFn::JsonStringify => 1,
+            BuiltinFn::CsvParse | BuiltinFn::CsvFormat => 1,
+
+            BuiltinFn::DateTime => 3,
+            BuiltinFn::DateTimeParse | BuiltinFn::DateTimeFormat => 1,
+            BuiltinFn::DateTimeFields => 1,
+            BuiltinFn::DateTimeAdd | BuiltinFn::DateTimeDiff | BuiltinFn::DateTimeToOffset => 2,
+            BuiltinFn::Duration => 0,
+            BuiltinFn::DurationSeconds => 1,
+
+            BuiltinFn::Get => 2,
+            BuiltinFn::Assoc => 3,
+            BuiltinFn::Dissoc => 2,
+            BuiltinFn::Keys | BuiltinFn::Values => 1,
+            BuiltinFn::Merge => return None,
+            BuiltinFn::Contains => 2,
+
+            BuiltinFn::Union | BuiltinFn::Intersection => return None,
+            BuiltinFn::Difference | BuiltinFn::Member | BuiltinFn::Insert | BuiltinFn::Remove => 2,
+
+            BuiltinFn::Partial => return None,
+            BuiltinFn::Curry => 1,
+            BuiltinFn::Compose => return None,
+            BuiltinFn::Flip => 1,
+            BuiltinFn::Arity => 1,
+
+            BuiltinFn::TypeOf => 1,
+            BuiltinFn::Show | BuiltinFn::Read | BuiltinFn::ParseNumber => 1,
+            BuiltinFn::Interpolate => return None,
+            BuiltinFn::IsNumber | BuiltinFn::IsInt | BuiltinFn::IsRational | BuiltinFn::IsFloat => {
+                1
+            }
+            BuiltinFn::IsBool | BuiltinFn::IsString | BuiltinFn::IsChar | BuiltinFn::IsBytes => 1,
+            BuiltinFn::IsSymbol | BuiltinFn::IsKeyword => 1,
+            BuiltinFn::IsList | BuiltinFn::IsMap | BuiltinFn::IsSet => 1,
+            BuiltinFn::IsRecord | BuiltinFn::IsVariant | BuiltinFn::IsRegex => 1,
+            BuiltinFn::IsDateTime | BuiltinFn::IsDuration => 1,
+            BuiltinFn::IsFn | BuiltinFn::IsMacro | BuiltinFn::IsIo => 1,
+            BuiltinFn::IsThunk | BuiltinFn::IsSeq | BuiltinFn::IsNil => 1,
+
+            BuiltinFn::Then | BuiltinFn::Bind => 2,
+            BuiltinFn::Return => 1,
+
+            BuiltinFn::ReadLine | BuiltinFn::Now => 0,
+            BuiltinFn::PrintLine => 1,
+        })
+    }
+
+    // The names of the named arguments a builtin accepts.
+    pub fn options(self) -> &'static [&'static str] {
+        match self {
+            BuiltinFn::Log => &["base"],
+            BuiltinFn::TypeOf => &["detailed"],
+            BuiltinFn::Range => &["step"],
+            BuiltinFn::PadLeft | BuiltinFn::PadRight => &["fill"],
+            BuiltinFn::JsonParse => &["ordered"],
+            BuiltinFn::JsonStringify => &["pretty"],
+            BuiltinFn::CsvParse => &["delimiter", "header"],
+            BuiltinFn::CsvFormat => &["delimiter", "columns"],
+            BuiltinFn::DateTime => &["hour", "minute", "second", "offset"],
+            BuiltinFn::DateTimeParse | BuiltinFn::DateTimeFormat => &["format"],
+            BuiltinFn::Duration => &["days", "hours", "minutes", "seconds", "milliseconds"],
+            BuiltinFn::ReadLine => &["prompt"],
+            _ => &[],
+        }
+    }
+
+    pub fn call(
+        self,
+        mut params: impl ExactSizeIterator<Item = Result<Value>>,
+        named: &[(&'static str, Value)],
+    ) -> Result<Value> {
+        for (i, (name, _)) in named.iter().enumerate() {
+            if !self.options().contains(name) || named[..i].iter().any(|(other, _)| other == name) {
+                return Err(BadProgram);
+            }
+        }
+
+        let option = |name: &str| {
+            named
+                .iter()
+                .find(|&&(other, _)| other == name)
+                .map(|(_, value)| value)
+        };
+
         match self {
-            BuiltinFn::Add | BuiltinFn::Sub | BuiltinFn::Mul | BuiltinFn::Div => {
+            // Without any arguments these give 0 and 1.
+            BuiltinFn::Add | BuiltinFn::Mul => {
+                let (mut result, op): (_, fn(&Value, &Value) -> Result<Value>) = match self {
+                    BuiltinFn::Add => (Value::Int(Int::Small(0)), number::add),
+                    BuiltinFn::Mul => (Value::Int(Int::Small(1)), number::mul),
+                    _ => unreachable!(),
+                };
+
+                for param in params {
+                    result = op(&result, &param?)?;
+                }
+
+                Ok(result)
+            }
+            BuiltinFn::Sub | BuiltinFn::Div => {
                 if params.len() != 2 {
                     return Err(BadProgram);
                 }
@@ -64,127 +585,1910 @@ impl BuiltinFn {
                 let a = params.next().unwrap()?;
                 let b = params.next().unwrap()?;
 
-                if let (Value::Number(a), Value::Number(b)) = (a, b) {
-                    Ok(Value::Number(match self {
-                        BuiltinFn::Add => a + b,
-                        BuiltinFn::Sub => a - b,
-                        BuiltinFn::Mul => a * b,
-                        BuiltinFn::Div => a / b,
-                        _ => unreachable!(),
-                    }))
-                } else {
-                    Err(BadProgram)
+                match self {
+                    BuiltinFn::Sub => number::sub(&a, &b),
+                    BuiltinFn::Div => number::div(&a, &b),
+                    _ => unreachable!(),
+                }
+            }
+            BuiltinFn::Quot | BuiltinFn::Rem => {
+                let (Some(Ok(Value::Int(a))), Some(Ok(Value::Int(b))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                match self {
+                    BuiltinFn::Quot => a.quot(&b),
+                    BuiltinFn::Rem => a.rem(&b),
+                    _ => unreachable!(),
+                }
+                .map(Value::Int)
+                .ok_or(BadProgram)
+            }
+            BuiltinFn::Float => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                number::to_f64(&value?).map(Value::Number)
+            }
+            BuiltinFn::Numerator | BuiltinFn::Denominator => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Int(match (self, value?) {
+                    (BuiltinFn::Numerator, Value::Int(int)) => int,
+                    (BuiltinFn::Denominator, Value::Int(_)) => Int::Small(1),
+                    (BuiltinFn::Numerator, Value::Rational(rational)) => rational.numerator(),
+                    (BuiltinFn::Denominator, Value::Rational(rational)) => rational.denominator(),
+                    _ => return Err(BadProgram),
+                }))
+            }
+            BuiltinFn::Mod | BuiltinFn::Pow => {
+                let (Some(a), Some(b), None) = (params.next(), params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                match self {
+                    BuiltinFn::Mod => number::modulo(&a?, &b?),
+                    BuiltinFn::Pow => number::pow(&a?, &b?),
+                    _ => unreachable!(),
+                }
+            }
+            BuiltinFn::Sqrt
+            | BuiltinFn::Abs
+            | BuiltinFn::Floor
+            | BuiltinFn::Ceil
+            | BuiltinFn::Round
+            | BuiltinFn::Truncate => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let value = value?;
+
+                match self {
+                    BuiltinFn::Sqrt => number::sqrt(&value),
+                    BuiltinFn::Abs => number::abs(&value),
+                    BuiltinFn::Floor => number::round(&value, Rounding::Floor),
+                    BuiltinFn::Ceil => number::round(&value, Rounding::Ceil),
+                    BuiltinFn::Round => number::round(&value, Rounding::Round),
+                    BuiltinFn::Truncate => number::round(&value, Rounding::Truncate),
+                    _ => unreachable!(),
                 }
             }
-            BuiltinFn::List => {
-                let mut list = Vec::with_capacity(params.len());
+            // The first of the smallest (or largest) numbers. NaN if any of them is NaN.
+            BuiltinFn::Min | BuiltinFn::Max => {
+                let wanted = match self {
+                    BuiltinFn::Min => Ordering::Less,
+                    BuiltinFn::Max => Ordering::Greater,
+                    _ => unreachable!(),
+                };
+
+                let mut result = params.next().ok_or(BadProgram)??;
 
                 for param in params {
                     let param = param?;
 
-                    list.push(param);
+                    match number::compare(&param, &result)? {
+                        Some(ordering) if ordering == wanted => result = param,
+                        Some(_) => {}
+                        None => result = Value::Number(f64::NAN),
+                    }
                 }
 
-                Ok(Value::List(Rc::new(list)))
+                // A single argument still has to be a number.
+                number::to_f64(&result)?;
+
+                Ok(result)
+            }
+            // These always give floats. `log` is base 10 unless there is a `base:`.
+            BuiltinFn::Exp
+            | BuiltinFn::Ln
+            | BuiltinFn::Log
+            | BuiltinFn::Sin
+            | BuiltinFn::Cos
+            | BuiltinFn::Tan
+            | BuiltinFn::Asin
+            | BuiltinFn::Acos
+            | BuiltinFn::Atan => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let value = number::to_f64(&value?)?;
+
+                Ok(Value::Number(match self {
+                    BuiltinFn::Exp => value.exp(),
+                    BuiltinFn::Ln => value.ln(),
+                    BuiltinFn::Log => match option("base") {
+                        Some(base) => value.log(number::to_f64(base)?),
+                        None => value.log10(),
+                    },
+                    BuiltinFn::Sin => value.sin(),
+                    BuiltinFn::Cos => value.cos(),
+                    BuiltinFn::Tan => value.tan(),
+                    BuiltinFn::Asin => value.asin(),
+                    BuiltinFn::Acos => value.acos(),
+                    BuiltinFn::Atan => value.atan(),
+                    _ => unreachable!(),
+                }))
+            }
+            BuiltinFn::Atan2 => {
+                let (Some(y), Some(x), None) = (params.next(), params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Number(
+                    number::to_f64(&y?)?.atan2(number::to_f64(&x?)?),
+                ))
+            }
+            // Integers and rationals are always finite.
+            BuiltinFn::IsNan | BuiltinFn::IsInfinite | BuiltinFn::IsFinite => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let value = match value? {
+                    Value::Number(float) => float,
+                    Value::Int(_) | Value::Rational(_) => 0.0,
+                    _ => return Err(BadProgram),
+                };
+
+                Ok(Value::Bool(match self {
+                    BuiltinFn::IsNan => value.is_nan(),
+                    BuiltinFn::IsInfinite => value.is_infinite(),
+                    BuiltinFn::IsFinite => value.is_finite(),
+                    _ => unreachable!(),
+                }))
+            }
+            BuiltinFn::Eq => {
+                let (Some(a), Some(b), None) = (params.next(), params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Bool(a? == b?))
+            }
+            BuiltinFn::Lt | BuiltinFn::Gt | BuiltinFn::Le | BuiltinFn::Ge => {
+                let (Some(a), Some(b), None) = (params.next(), params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                // Dates and durations can be compared as well.
+                let ordering = match (a?, b?) {
+                    (Value::DateTime(a), Value::DateTime(b)) => Some(a.cmp(&b)),
+                    (Value::Duration(a), Value::Duration(b)) => Some(a.cmp(&b)),
+                    (a, b) => number::compare(&a, &b)?,
+                };
+
+                Ok(Value::Bool(match self {
+                    BuiltinFn::Lt => ordering == Some(Ordering::Less),
+                    BuiltinFn::Gt => ordering == Some(Ordering::Greater),
+                    BuiltinFn::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    BuiltinFn::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                    _ => unreachable!(),
+                }))
             }
+            BuiltinFn::List => params.collect::<Result<_>>().map(Value::List),
             BuiltinFn::Map => {
-                let (Some(Ok(Value::List(list))), Some(Ok(Value::Fn(f))), None) =
+                let (Some(Ok(list)), Some(Ok(Value::Fn(f))), None) =
                     (params.next(), params.next(), params.next())
                 else {
                     return Err(BadProgram);
                 };
 
-                let output = list
-                    .iter()
-                    .map(|v| f.call([Ok(v.clone())].into_iter()))
-                    .collect::<Result<_>>()?;
+                match list {
+                    Value::List(list) => {
+                        let output = list
+                            .iter()
+                            .map(|v| f.call([Ok(v.clone())].into_iter()))
+                            .collect::<Result<_>>()?;
+
+                        Ok(Value::List(output))
+                    }
+                    Value::Set(set) => set
+                        .iter()
+                        .map(|v| {
+                            let v = f.call([Ok(v.clone())].into_iter())?;
 
-                Ok(Value::List(Rc::new(output)))
+                            compare::is_key(&v).then_some(v).ok_or(BadProgram)
+                        })
+                        .collect::<Result<_>>()
+                        .map(Value::Set),
+                    Value::Seq(seq) => Ok(Value::Seq(seq.map(f))),
+                    _ => Err(BadProgram),
+                }
             }
-            BuiltinFn::Then => {
-                let (Some(Ok(Value::Io(a))), Some(Ok(Value::Io(b))), None) =
+            BuiltinFn::Cons => {
+                let (Some(value), Some(Ok(Value::List(list))), None) =
                     (params.next(), params.next(), params.next())
                 else {
                     return Err(BadProgram);
                 };
 
-                Ok(Value::Io(a.then(b)))
+                Ok(Value::List(list.push_front(value?)))
             }
-            BuiltinFn::Bind => {
-                let (Some(Ok(Value::Io(a))), Some(Ok(Value::Fn(b))), None) =
+            BuiltinFn::Append => {
+                let (Some(Ok(Value::List(list))), Some(value), None) =
                     (params.next(), params.next(), params.next())
                 else {
                     return Err(BadProgram);
                 };
 
-                a.bind(&b).map(Value::Io)
+                Ok(Value::List(list.push_back(value?)))
             }
-            BuiltinFn::Return => {
-                if params.len() != 1 {
+            BuiltinFn::Nth => {
+                let (Some(Ok(list)), Some(Ok(Value::Int(index))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
                     return Err(BadProgram);
+                };
+
+                let index = index.to_usize().ok_or(BadProgram)?;
+
+                match list {
+                    Value::List(list) => list.get(index).cloned(),
+                    Value::Seq(seq) => seq.skip(index).next()?.map(|(head, _)| head),
+                    _ => None,
                 }
+                .ok_or(BadProgram)
+            }
+            BuiltinFn::First => {
+                let (Some(Ok(list)), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
 
-                Ok(Value::Io(Rc::new(Io::Done(params.next().unwrap()?))))
+                Ok(match list {
+                    Value::List(list) => list.first().cloned().unwrap_or(Value::Nil),
+                    Value::Seq(seq) => seq.next()?.map_or(Value::Nil, |(head, _)| head),
+                    _ => return Err(BadProgram),
+                })
             }
-            BuiltinFn::ReadLine => {
-                if params.len() != 0 {
+            BuiltinFn::Rest => {
+                let (Some(Ok(list)), None) = (params.next(), params.next()) else {
                     return Err(BadProgram);
-                }
+                };
 
-                Ok(Value::Io(Rc::new(Io::ReadLine(Function::Builtin(
-                    BuiltinFn::Return,
-                )))))
+                Ok(match list {
+                    Value::List(list) => Value::List(list.skip(1)),
+                    Value::Seq(seq) => {
+                        Value::Seq(seq.next()?.map_or(Seq::empty(), |(_, tail)| tail))
+                    }
+                    _ => return Err(BadProgram),
+                })
             }
-            BuiltinFn::PrintLine => {
-                let Some(Ok(Value::String(line))) = params.next() else {
+            BuiltinFn::Filter => {
+                let (Some(Ok(list)), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
                     return Err(BadProgram);
                 };
 
-                Ok(Value::Io(Rc::new(Io::PrintLine(
-                    line,
-                    Rc::new(Io::Done(Value::Nil)),
-                ))))
+                match list {
+                    Value::List(list) => {
+                        let mut output = Vec::new();
+
+                        for value in list {
+                            if f.test(value.clone())? {
+                                output.push(value);
+                            }
+                        }
+
+                        Ok(Value::List(output.into()))
+                    }
+                    Value::Set(set) => {
+                        let mut output = set.clone();
+
+                        for value in set {
+                            if !f.test(value.clone())? {
+                                output.remove(&value);
+                            }
+                        }
+
+                        Ok(Value::Set(output))
+                    }
+                    Value::Seq(seq) => Ok(Value::Seq(seq.filter(f))),
+                    _ => Err(BadProgram),
+                }
             }
-        }
-    }
-}
+            BuiltinFn::Fold => {
+                let (Some(Ok(list)), Some(init), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
 
-impl BuiltinMacro {
-    pub fn call(self, scope: &Rc<Scope>, content: &[Value]) -> Result<Value> {
-        match self {
-            BuiltinMacro::Block => eval_block(scope.clone(), content),
-            BuiltinMacro::Do => eval_do_block(scope, content).map(Value::Io),
-            BuiltinMacro::Fn => {
-                if content.len() < 2 {
+                let mut acc = init?;
+
+                for value in values(list)? {
+                    acc = f.call([Ok(acc), value].into_iter())?;
+                }
+
+                Ok(acc)
+            }
+            // Like `fold`, but starting with the first value. Reducing an empty list is an error.
+            BuiltinFn::Reduce => {
+                let (Some(Ok(list)), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
                     return Err(BadProgram);
+                };
+
+                let mut values = values(list)?;
+
+                let mut acc = values.next().ok_or(BadProgram)??;
+
+                for value in values {
+                    acc = f.call([Ok(acc), value].into_iter())?;
                 }
 
-                let Value::List(ref params) = content[0] else {
+                Ok(acc)
+            }
+            BuiltinFn::Reverse => {
+                let (Some(Ok(Value::List(list))), None) = (params.next(), params.next()) else {
                     return Err(BadProgram);
                 };
 
-                for (i, param) in params.iter().enumerate() {
-                    if let &Value::Symbol(name) = param {
-                        // Duplicate parameter names are not allowed.
-                        if params[..i].iter().any(|other| {
-                            if let &Value::Symbol(other) = other {
-                                other == name
-                            } else {
-                                unreachable!()
-                            }
-                        }) {
+                Ok(Value::List(list.iter().rev().cloned().collect()))
+            }
+            // Concatenates either strings or lists, depending on what the first argument is.
+            BuiltinFn::Concat => {
+                let values = params.collect::<Result<Vec<_>>>()?;
+
+                if let Some(Value::String(_)) = values.first() {
+                    let mut output = String::new();
+
+                    for value in values {
+                        let Value::String(string) = value else {
+                            return Err(BadProgram);
+                        };
+
+                        output.push_str(&string);
+                    }
+
+                    Ok(Value::String(Rc::new(output)))
+                } else {
+                    let mut output = Vec::new();
+
+                    for value in values {
+                        let Value::List(list) = value else {
                             return Err(BadProgram);
+                        };
+
+                        output.extend(list);
+                    }
+
+                    Ok(Value::List(output.into()))
+                }
+            }
+            BuiltinFn::Sort => {
+                let (Some(Ok(Value::List(list))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let mut output = list.into_iter().collect::<Vec<_>>();
+                output.sort();
+
+                Ok(Value::List(output.into()))
+            }
+            // Sorts by the keys `f` returns for the values. Values with equal keys keep their
+            // order.
+            BuiltinFn::SortBy => {
+                let (Some(Ok(Value::List(list))), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let mut keyed = list
+                    .into_iter()
+                    .map(|value| Ok((f.call([Ok(value.clone())].into_iter())?, value)))
+                    .collect::<Result<Vec<_>>>()?;
+
+                keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                Ok(Value::List(
+                    keyed.into_iter().map(|(_, value)| value).collect(),
+                ))
+            }
+            BuiltinFn::Zip => {
+                let (Some(Ok(a)), Some(Ok(b)), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                match (a, b) {
+                    (Value::List(a), Value::List(b)) => Ok(Value::List(
+                        a.into_iter()
+                            .zip(b)
+                            .map(|(a, b)| Value::List([a, b].into_iter().collect()))
+                            .collect(),
+                    )),
+                    (
+                        a @ (Value::List(_) | Value::Seq(_)),
+                        b @ (Value::List(_) | Value::Seq(_)),
+                    ) => Ok(Value::Seq(Seq::from_value(a)?.zip(&Seq::from_value(b)?))),
+                    _ => Err(BadProgram),
+                }
+            }
+            // Flattens nested lists all the way down.
+            BuiltinFn::Flatten => {
+                let (Some(Ok(Value::List(list))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                fn flatten(list: List, output: &mut Vec<Value>) {
+                    for value in list {
+                        match value {
+                            Value::List(list) => flatten(list, output),
+                            value => output.push(value),
                         }
-                    } else {
-                        return Err(BadProgram);
                     }
                 }
 
-                Ok(Value::Fn(Function::User(Rc::new(UserFn {
-                    scope: scope.clone(),
-                    params: params.clone(),
-                    content: content[1..].to_vec(),
-                }))))
+                let mut output = Vec::new();
+                flatten(list, &mut output);
+
+                Ok(Value::List(output.into()))
+            }
+            BuiltinFn::Any | BuiltinFn::All => {
+                let (Some(Ok(list)), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                // `any` stops at the first `true`, `all` at the first `false`.
+                let stop = matches!(self, BuiltinFn::Any);
+
+                for value in values(list)? {
+                    if f.test(value?)? == stop {
+                        return Ok(Value::Bool(stop));
+                    }
+                }
+
+                Ok(Value::Bool(!stop))
+            }
+            BuiltinFn::Find => {
+                let (Some(Ok(list)), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                for value in values(list)? {
+                    let value = value?;
+
+                    if f.test(value.clone())? {
+                        return Ok(value);
+                    }
+                }
+
+                Ok(Value::Nil)
+            }
+            BuiltinFn::IndexOf => {
+                let (Some(Ok(list)), Some(needle), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let needle = needle?;
+
+                for (i, value) in values(list)?.enumerate() {
+                    if value? == needle {
+                        return Ok(Value::Int(Int::from(i)));
+                    }
+                }
+
+                Ok(Value::Nil)
+            }
+            // Returns a map from each key `f` returns to a list of the values it returned it for.
+            BuiltinFn::GroupBy => {
+                let (Some(Ok(list)), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let mut groups = OrdMap::new();
+
+                for value in values(list)? {
+                    let value = value?;
+                    let key = f.call([Ok(value.clone())].into_iter())?;
+
+                    if !compare::is_key(&key) {
+                        return Err(BadProgram);
+                    }
+
+                    let group = match groups.get(&key) {
+                        Some(Value::List(group)) => group.push_back(value),
+                        _ => List::from(vec![value]),
+                    };
+
+                    groups.insert(key, Value::List(group));
+                }
+
+                Ok(Value::Map(groups))
+            }
+            // Splits a list into the values `f` returns `true` for and the ones it doesn't.
+            BuiltinFn::Partition => {
+                let (Some(Ok(list)), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let (mut yes, mut no) = (Vec::new(), Vec::new());
+
+                for value in values(list)? {
+                    let value = value?;
+
+                    if f.test(value.clone())? {
+                        yes.push(value);
+                    } else {
+                        no.push(value);
+                    }
+                }
+
+                Ok(Value::List(
+                    [Value::List(yes.into()), Value::List(no.into())]
+                        .into_iter()
+                        .collect(),
+                ))
+            }
+            // Keeps the first of each group of equal values.
+            BuiltinFn::Unique => {
+                let (Some(Ok(Value::List(list))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let mut seen = OrdSet::new();
+
+                Ok(Value::List(
+                    list.into_iter()
+                        .filter(|value| seen.insert(value.clone()).is_none())
+                        .collect(),
+                ))
+            }
+            BuiltinFn::Force => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                match value? {
+                    Value::Thunk(thunk) => thunk.force(),
+                    value => Ok(value),
+                }
+            }
+            BuiltinFn::LazyCons => {
+                let (Some(head), Some(tail), None) = (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Seq(Seq::cons(head?, Seq::from_value(tail?)?)))
+            }
+            // Lists stay as they are, so this works on whatever `map` or `filter` return.
+            BuiltinFn::ToList => {
+                let (Some(Ok(value)), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                match value {
+                    Value::Seq(seq) => Ok(Value::List(seq.to_vec()?.into())),
+                    list @ Value::List(_) => Ok(list),
+                    _ => Err(BadProgram),
+                }
+            }
+            BuiltinFn::Range => {
+                let (Some(start), Some(end), None) = (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let (start, end) = (start?, end?);
+                let step = option("step").cloned().unwrap_or(Value::Int(Int::Small(1)));
+
+                // Also makes sure that both ends are numbers.
+                number::compare(&start, &end)?;
+
+                let direction = match number::compare(&step, &Value::Int(Int::Small(0)))? {
+                    Some(Ordering::Greater) => Ordering::Less,
+                    Some(Ordering::Less) => Ordering::Greater,
+                    // A step of zero (or NaN) would never get anywhere.
+                    _ => return Err(BadProgram),
+                };
+
+                Ok(Value::Seq(range(start, end, step, direction)))
+            }
+            // Repeats a value `count` times, or forever if `count` is `infinity`. Strings get
+            // repeated into one longer string.
+            BuiltinFn::Repeat => {
+                let (Some(Ok(value)), Some(Ok(count)), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let count = match count {
+                    Value::Int(count) => count.to_usize().ok_or(BadProgram)?,
+                    Value::Number(f64::INFINITY) => return Ok(Value::Seq(Seq::repeat(value))),
+                    _ => return Err(BadProgram),
+                };
+
+                Ok(match value {
+                    Value::String(string) => Value::String(Rc::new(string.repeat(count))),
+                    value => Value::List(std::iter::repeat_n(value, count).collect()),
+                })
+            }
+            BuiltinFn::Iterate => {
+                let (Some(value), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Seq(Seq::iterate(value?, f)))
+            }
+            BuiltinFn::Take | BuiltinFn::Drop => {
+                let (Some(Ok(list)), Some(Ok(Value::Int(count))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let count = count.to_usize().ok_or(BadProgram)?;
+
+                Ok(match (self, list) {
+                    (BuiltinFn::Take, Value::List(list)) => Value::List(list.take(count)),
+                    (BuiltinFn::Drop, Value::List(list)) => Value::List(list.skip(count)),
+                    (BuiltinFn::Take, Value::Seq(seq)) => Value::Seq(seq.take(count)),
+                    (BuiltinFn::Drop, Value::Seq(seq)) => Value::Seq(seq.skip(count)),
+                    _ => return Err(BadProgram),
+                })
+            }
+            BuiltinFn::TakeWhile => {
+                let (Some(Ok(list)), Some(Ok(Value::Fn(f))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                match list {
+                    Value::List(list) => {
+                        let mut count = 0;
+
+                        for value in list.iter() {
+                            match f.call([Ok(value.clone())].into_iter())? {
+                                Value::Bool(true) => count += 1,
+                                Value::Bool(false) => break,
+                                _ => return Err(BadProgram),
+                            }
+                        }
+
+                        Ok(Value::List(list.take(count)))
+                    }
+                    Value::Seq(seq) => Ok(Value::Seq(seq.take_while(f))),
+                    _ => Err(BadProgram),
+                }
+            }
+            BuiltinFn::Length | BuiltinFn::ByteLength | BuiltinFn::GraphemeLength => {
+                let (Some(Ok(value)), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Int(Int::from(match (self, value) {
+                    (BuiltinFn::Length, Value::List(list)) => list.len(),
+                    (BuiltinFn::Length, Value::Map(map)) => map.len(),
+                    (BuiltinFn::Length, Value::Set(set)) => set.len(),
+                    (BuiltinFn::Length, Value::String(string)) => string.chars().count(),
+                    (BuiltinFn::ByteLength, Value::String(string)) => string.len(),
+                    (BuiltinFn::GraphemeLength, Value::String(string)) => {
+                        string.graphemes(true).count()
+                    }
+                    (BuiltinFn::Length, Value::Bytes(bytes)) => bytes.len(),
+                    _ => return Err(BadProgram),
+                })))
+            }
+            BuiltinFn::CharAt => {
+                let (Some(Ok(Value::String(string))), Some(Ok(Value::Int(index))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                string
+                    .chars()
+                    .nth(index.to_usize().ok_or(BadProgram)?)
+                    .map(Value::Char)
+                    .ok_or(BadProgram)
+            }
+            BuiltinFn::Chars => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::List(string.chars().map(Value::Char).collect()))
+            }
+            BuiltinFn::Graphemes => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::List(
+                    string
+                        .graphemes(true)
+                        .map(|grapheme| Value::String(Rc::new(grapheme.to_string())))
+                        .collect(),
+                ))
+            }
+            BuiltinFn::Substring => {
+                let (
+                    Some(Ok(Value::String(string))),
+                    Some(Ok(Value::Int(start))),
+                    Some(Ok(Value::Int(end))),
+                    None,
+                ) = (params.next(), params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let start = start.to_usize().ok_or(BadProgram)?;
+                let end = end.to_usize().ok_or(BadProgram)?;
+
+                if start > end {
+                    return Err(BadProgram);
+                }
+
+                // The indices count characters, so they need to be turned into byte offsets.
+                let mut offsets = string
+                    .char_indices()
+                    .map(|(offset, _)| offset)
+                    .chain([string.len()]);
+
+                let start_offset = offsets.nth(start).ok_or(BadProgram)?;
+                let end_offset = if start == end {
+                    start_offset
+                } else {
+                    offsets.nth(end - start - 1).ok_or(BadProgram)?
+                };
+
+                Ok(Value::String(Rc::new(
+                    string[start_offset..end_offset].to_string(),
+                )))
+            }
+            BuiltinFn::Split => {
+                let (Some(Ok(Value::String(string))), Some(Ok(Value::String(separator))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                // Use `chars` to split a string into its characters.
+                if separator.is_empty() {
+                    return Err(BadProgram);
+                }
+
+                Ok(Value::List(
+                    string
+                        .split(separator.as_str())
+                        .map(|part| Value::String(Rc::new(part.to_string())))
+                        .collect(),
+                ))
+            }
+            BuiltinFn::Join => {
+                let (Some(Ok(Value::List(list))), Some(Ok(Value::String(separator))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let mut output = String::new();
+
+                for (i, value) in list.into_iter().enumerate() {
+                    let Value::String(part) = value else {
+                        return Err(BadProgram);
+                    };
+
+                    if i > 0 {
+                        output.push_str(&separator);
+                    }
+
+                    output.push_str(&part);
+                }
+
+                Ok(Value::String(Rc::new(output)))
+            }
+            BuiltinFn::Lines => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::List(
+                    string
+                        .lines()
+                        .map(|line| Value::String(Rc::new(line.to_string())))
+                        .collect(),
+                ))
+            }
+            BuiltinFn::Trim | BuiltinFn::Upper | BuiltinFn::Lower => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::String(Rc::new(match self {
+                    BuiltinFn::Trim => string.trim().to_string(),
+                    BuiltinFn::Upper => string.to_uppercase(),
+                    BuiltinFn::Lower => string.to_lowercase(),
+                    _ => unreachable!(),
+                })))
+            }
+            BuiltinFn::StartsWith | BuiltinFn::EndsWith => {
+                let (Some(Ok(Value::String(string))), Some(Ok(Value::String(affix))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Bool(match self {
+                    BuiltinFn::StartsWith => string.starts_with(affix.as_str()),
+                    BuiltinFn::EndsWith => string.ends_with(affix.as_str()),
+                    _ => unreachable!(),
+                }))
+            }
+            // Replaces every occurrence.
+            BuiltinFn::Replace => {
+                let (
+                    Some(Ok(Value::String(string))),
+                    Some(Ok(Value::String(from))),
+                    Some(Ok(Value::String(to))),
+                    None,
+                ) = (params.next(), params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::String(Rc::new(string.replace(from.as_str(), &to))))
+            }
+            // Pads a string with spaces (or the `fill:` character) until it is `width` characters
+            // long.
+            BuiltinFn::PadLeft | BuiltinFn::PadRight => {
+                let (Some(Ok(Value::String(string))), Some(Ok(Value::Int(width))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let fill = match option("fill") {
+                    Some(&Value::Char(fill)) => fill,
+                    Some(_) => return Err(BadProgram),
+                    None => ' ',
+                };
+
+                let width = width.to_usize().ok_or(BadProgram)?;
+                let padding = fill
+                    .to_string()
+                    .repeat(width.saturating_sub(string.chars().count()));
+
+                Ok(Value::String(Rc::new(match self {
+                    BuiltinFn::PadLeft => padding + &string,
+                    BuiltinFn::PadRight => format!("{string}{padding}"),
+                    _ => unreachable!(),
+                })))
+            }
+            BuiltinFn::Regex => {
+                let (Some(pattern), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                compiled(pattern?).map(Value::Regex)
+            }
+            BuiltinFn::RegexMatch | BuiltinFn::RegexFindAll | BuiltinFn::RegexSplit => {
+                let (Some(Ok(Value::String(string))), Some(pattern), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let regex = compiled(pattern?)?;
+                let to_value = |part: &str| Value::String(Rc::new(part.to_string()));
+
+                Ok(match self {
+                    BuiltinFn::RegexMatch => Value::Bool(regex.is_match(&string)),
+                    BuiltinFn::RegexFindAll => Value::List(
+                        regex
+                            .find_iter(&string)
+                            .map(|found| to_value(found.as_str()))
+                            .collect(),
+                    ),
+                    BuiltinFn::RegexSplit => {
+                        Value::List(regex.split(&string).map(to_value).collect())
+                    }
+                    _ => unreachable!(),
+                })
+            }
+            // The groups of the first match, or nil if there is none. Groups that didn't take part
+            // in the match are nil as well. If the regex has named groups this is a map from the
+            // names (as keywords) or the indices of unnamed groups to the groups, otherwise it's a
+            // list. Either way the whole match is group 0.
+            BuiltinFn::RegexCaptures => {
+                let (Some(Ok(Value::String(string))), Some(pattern), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let regex = compiled(pattern?)?;
+
+                let Some(captures) = regex.captures(&string) else {
+                    return Ok(Value::Nil);
+                };
+
+                let group = |i: usize| {
+                    captures.get(i).map_or(Value::Nil, |group| {
+                        Value::String(Rc::new(group.as_str().to_string()))
+                    })
+                };
+
+                if regex.capture_names().flatten().next().is_none() {
+                    return Ok(Value::List((0..captures.len()).map(group).collect()));
+                }
+
+                Ok(Value::Map(
+                    regex
+                        .capture_names()
+                        .enumerate()
+                        .map(|(i, name)| {
+                            let key = match name {
+                                Some(name) => Value::Keyword(Keyword::new(name)),
+                                None => Value::Int(Int::from(i)),
+                            };
+
+                            (key, group(i))
+                        })
+                        .collect(),
+                ))
+            }
+            // Replaces every match. `$1` or `$name` in the replacement stand for groups.
+            BuiltinFn::RegexReplace => {
+                let (
+                    Some(Ok(Value::String(string))),
+                    Some(pattern),
+                    Some(Ok(Value::String(replacement))),
+                    None,
+                ) = (params.next(), params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let regex = compiled(pattern?)?;
+
+                Ok(Value::String(Rc::new(
+                    regex
+                        .replace_all(&string, replacement.as_str())
+                        .into_owned(),
+                )))
+            }
+            BuiltinFn::BytesSlice => {
+                let (
+                    Some(Ok(Value::Bytes(bytes))),
+                    Some(Ok(Value::Int(start))),
+                    Some(Ok(Value::Int(end))),
+                    None,
+                ) = (params.next(), params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let start = start.to_usize().ok_or(BadProgram)?;
+                let end = end.to_usize().ok_or(BadProgram)?;
+
+                Ok(Value::Bytes(Rc::new(
+                    bytes.get(start..end).ok_or(BadProgram)?.to_vec(),
+                )))
+            }
+            BuiltinFn::BytesConcat => {
+                let mut output = Vec::new();
+
+                for param in params {
+                    let Value::Bytes(bytes) = param? else {
+                        return Err(BadProgram);
+                    };
+
+                    output.extend_from_slice(&bytes);
+                }
+
+                Ok(Value::Bytes(Rc::new(output)))
+            }
+            BuiltinFn::Utf8Encode => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Bytes(Rc::new(string.as_bytes().to_vec())))
+            }
+            BuiltinFn::Utf8Decode => {
+                let (Some(Ok(Value::Bytes(bytes))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let string = String::from_utf8(bytes.to_vec()).map_err(|_| BadProgram)?;
+
+                Ok(Value::String(Rc::new(string)))
+            }
+            BuiltinFn::HexEncode | BuiltinFn::Base64Encode => {
+                let (Some(Ok(Value::Bytes(bytes))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::String(Rc::new(match self {
+                    BuiltinFn::HexEncode => encoding::hex_encode(&bytes),
+                    BuiltinFn::Base64Encode => encoding::base64_encode(&bytes),
+                    _ => unreachable!(),
+                })))
+            }
+            BuiltinFn::HexDecode | BuiltinFn::Base64Decode => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                match self {
+                    BuiltinFn::HexDecode => encoding::hex_decode(&string),
+                    BuiltinFn::Base64Decode => encoding::base64_decode(&string),
+                    _ => unreachable!(),
+                }
+                .map(|bytes| Value::Bytes(Rc::new(bytes)))
+                .ok_or(BadProgram)
+            }
+            BuiltinFn::JsonParse => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let ordered = match option("ordered") {
+                    Some(&Value::Bool(ordered)) => ordered,
+                    Some(_) => return Err(BadProgram),
+                    None => false,
+                };
+
+                json::parse(&string, ordered)
+            }
+            BuiltinFn::JsonStringify => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let pretty = match option("pretty") {
+                    Some(&Value::Bool(pretty)) => pretty,
+                    Some(_) => return Err(BadProgram),
+                    None => false,
+                };
+
+                Ok(Value::String(Rc::new(json::stringify(&value?, pretty)?)))
+            }
+            // With `header: true` the first row holds the column names and every other row becomes
+            // a map from those to its fields.
+            BuiltinFn::CsvParse => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let header = match option("header") {
+                    Some(&Value::Bool(header)) => header,
+                    Some(_) => return Err(BadProgram),
+                    None => false,
+                };
+
+                let mut rows = csv::parse(&string, delimiter(option("delimiter"))?)
+                    .ok_or(BadProgram)?
+                    .into_iter()
+                    .map(|row| row.into_iter().map(|field| Value::String(Rc::new(field))));
+
+                if !header {
+                    return Ok(Value::List(
+                        rows.map(|row| Value::List(row.collect())).collect(),
+                    ));
+                }
+
+                let Some(columns) = rows.next() else {
+                    return Ok(Value::List(List::new()));
+                };
+
+                let columns: Vec<Value> = columns.collect();
+
+                // Two columns with the same name would end up as a single key.
+                if (1..columns.len()).any(|i| columns[..i].contains(&columns[i])) {
+                    return Err(BadProgram);
+                }
+
+                Ok(Value::List(
+                    rows.map(|row| {
+                        let row: Vec<Value> = row.collect();
+
+                        if row.len() != columns.len() {
+                            return Err(BadProgram);
+                        }
+
+                        Ok(Value::Map(columns.iter().cloned().zip(row).collect()))
+                    })
+                    .collect::<Result<_>>()?,
+                ))
+            }
+            // Rows can be lists or maps. `columns:` adds a header row, and maps need it to know
+            // which fields go where, since their keys are sorted instead of being in the order of
+            // the columns. Strings are written as they are, nil as an empty field and everything
+            // else the way `show` prints it.
+            BuiltinFn::CsvFormat => {
+                let (Some(Ok(Value::List(rows))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let columns = match option("columns") {
+                    Some(Value::List(columns)) => Some(columns.clone()),
+                    Some(_) => return Err(BadProgram),
+                    None => None,
+                };
+
+                let field = |value: &Value| match value {
+                    Value::String(string) => string.to_string(),
+                    Value::Nil => String::new(),
+                    value => value.to_string(),
+                };
+
+                let header = columns
+                    .iter()
+                    .map(|columns| columns.iter().map(field).collect());
+
+                let rows = rows
+                    .iter()
+                    .map(|row| match (row, &columns) {
+                        (Value::List(row), Some(columns)) if row.len() != columns.len() => {
+                            Err(BadProgram)
+                        }
+                        (Value::List(row), _) => Ok(row.iter().map(field).collect()),
+                        (Value::Map(map), Some(columns)) => Ok(columns
+                            .iter()
+                            .map(|column| map.get(column).map_or(String::new(), field))
+                            .collect()),
+                        _ => Err(BadProgram),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(Value::String(Rc::new(csv::format(
+                    header.chain(rows),
+                    delimiter(option("delimiter"))?,
+                ))))
+            }
+            // The time of day and the offset are options, which default to midnight UTC.
+            BuiltinFn::DateTime => {
+                let (Some(year), Some(month), Some(day), None) =
+                    (params.next(), params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let part = |value: Option<&Value>| match value {
+                    Some(Value::Int(Int::Small(int))) => Some(*int),
+                    Some(_) => None,
+                    None => Some(0),
+                };
+
+                let unsigned = |value| part(value).and_then(|int| u32::try_from(int).ok());
+
+                let offset = match option("offset") {
+                    Some(Value::Duration(duration)) => time::offset(duration)?,
+                    Some(_) => return Err(BadProgram),
+                    None => chrono::FixedOffset::east_opt(0).unwrap(),
+                };
+
+                let (Some(year), Some(month), Some(day), Some(hour), Some(minute), Some(second)) = (
+                    part(Some(&year?)).and_then(|year| i32::try_from(year).ok()),
+                    unsigned(Some(&month?)),
+                    unsigned(Some(&day?)),
+                    unsigned(option("hour")),
+                    unsigned(option("minute")),
+                    unsigned(option("second")),
+                ) else {
+                    return Err(BadProgram);
+                };
+
+                chrono::NaiveDate::from_ymd_opt(year, month, day)
+                    .and_then(|date| date.and_hms_opt(hour, minute, second))
+                    .and_then(|naive| naive.and_local_timezone(offset).single())
+                    .map(Value::DateTime)
+                    .ok_or(BadProgram)
+            }
+            // Gives nil if the string isn't a date.
+            BuiltinFn::DateTimeParse => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let format = match option("format") {
+                    Some(Value::String(format)) => Some(format.as_str()),
+                    Some(_) => return Err(BadProgram),
+                    None => None,
+                };
+
+                Ok(time::parse(&string, format).map_or(Value::Nil, Value::DateTime))
+            }
+            BuiltinFn::DateTimeFormat => {
+                let (Some(Ok(Value::DateTime(datetime))), None) = (params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let format = match option("format") {
+                    Some(Value::String(format)) => Some(format.as_str()),
+                    Some(_) => return Err(BadProgram),
+                    None => None,
+                };
+
+                Ok(Value::String(Rc::new(time::format(&datetime, format)?)))
+            }
+            BuiltinFn::DateTimeFields => {
+                let (Some(Ok(Value::DateTime(datetime))), None) = (params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(time::fields(&datetime))
+            }
+            BuiltinFn::DateTimeAdd => {
+                let (
+                    Some(Ok(Value::DateTime(datetime))),
+                    Some(Ok(Value::Duration(duration))),
+                    None,
+                ) = (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                datetime
+                    .checked_add_signed(duration)
+                    .map(Value::DateTime)
+                    .ok_or(BadProgram)
+            }
+            // How much later the first one is than the second one.
+            BuiltinFn::DateTimeDiff => {
+                let (Some(Ok(Value::DateTime(a))), Some(Ok(Value::DateTime(b))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Duration(a - b))
+            }
+            // The same moment, but in another time zone.
+            BuiltinFn::DateTimeToOffset => {
+                let (Some(Ok(Value::DateTime(datetime))), Some(Ok(Value::Duration(offset))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::DateTime(
+                    datetime.with_timezone(&time::offset(&offset)?),
+                ))
+            }
+            // The amounts of all the units get added up, so `[duration hours: 1 minutes: 30]` is an
+            // hour and a half.
+            BuiltinFn::Duration => {
+                if params.len() != 0 {
+                    return Err(BadProgram);
+                }
+
+                time::duration(
+                    time::UNITS
+                        .iter()
+                        .filter_map(|&(unit, nanos)| Some((option(unit)?, nanos))),
+                )
+                .map(Value::Duration)
+            }
+            BuiltinFn::DurationSeconds => {
+                let (Some(Ok(Value::Duration(duration))), None) = (params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(time::seconds(&duration))
+            }
+            BuiltinFn::Get => {
+                let (Some(Ok(Value::Map(map))), Some(key), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(map.get(&key?).cloned().unwrap_or(Value::Nil))
+            }
+            BuiltinFn::Assoc => {
+                let (Some(Ok(Value::Map(map))), Some(key), Some(value), None) =
+                    (params.next(), params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let key = key?;
+
+                if !compare::is_key(&key) {
+                    return Err(BadProgram);
+                }
+
+                Ok(Value::Map(map.update(key, value?)))
+            }
+            BuiltinFn::Dissoc => {
+                let (Some(Ok(Value::Map(map))), Some(key), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Map(map.without(&key?)))
+            }
+            BuiltinFn::Keys | BuiltinFn::Values => {
+                let (Some(Ok(Value::Map(map))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::List(match self {
+                    BuiltinFn::Keys => map.keys().cloned().collect(),
+                    BuiltinFn::Values => map.values().cloned().collect(),
+                    _ => unreachable!(),
+                }))
+            }
+            BuiltinFn::Merge => {
+                let mut merged = OrdMap::new();
+
+                for param in params {
+                    let Value::Map(map) = param? else {
+                        return Err(BadProgram);
+                    };
+
+                    // Later maps take precedence.
+                    merged = merged.union_with(map, |_, later| later);
+                }
+
+                Ok(Value::Map(merged))
+            }
+            // Whether a map contains a key or a string contains a substring.
+            BuiltinFn::Contains => {
+                let (Some(Ok(container)), Some(value), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Bool(match (container, value?) {
+                    (Value::Map(map), key) => map.contains_key(&key),
+                    (Value::String(string), Value::String(part)) => string.contains(part.as_str()),
+                    _ => return Err(BadProgram),
+                }))
+            }
+            BuiltinFn::Union | BuiltinFn::Intersection => {
+                let mut sets = params.map(|param| match param? {
+                    Value::Set(set) => Ok(set),
+                    _ => Err(BadProgram),
+                });
+
+                let mut result = sets.next().ok_or(BadProgram)??;
+
+                for set in sets {
+                    result = match self {
+                        BuiltinFn::Union => result.union(set?),
+                        BuiltinFn::Intersection => result.intersection(set?),
+                        _ => unreachable!(),
+                    };
+                }
+
+                Ok(Value::Set(result))
+            }
+            BuiltinFn::Difference => {
+                let (Some(Ok(Value::Set(a))), Some(Ok(Value::Set(b))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Set(a.relative_complement(b)))
+            }
+            BuiltinFn::Member => {
+                let (Some(Ok(Value::Set(set))), Some(value), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Bool(set.contains(&value?)))
+            }
+            BuiltinFn::Insert => {
+                let (Some(Ok(Value::Set(set))), Some(value), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                let value = value?;
+
+                if !compare::is_key(&value) {
+                    return Err(BadProgram);
+                }
+
+                Ok(Value::Set(set.update(value)))
+            }
+            BuiltinFn::Remove => {
+                let (Some(Ok(Value::Set(set))), Some(value), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Set(set.without(&value?)))
+            }
+            BuiltinFn::Partial => {
+                let Some(Ok(Value::Fn(f))) = params.next() else {
+                    return Err(BadProgram);
+                };
+
+                let bound = params.collect::<Result<Vec<_>>>()?;
+
+                let arity = match f.arity() {
+                    Some(arity) => Some(arity.checked_sub(bound.len()).ok_or(BadProgram)?),
+                    None => None,
+                };
+
+                Ok(Value::Fn(Function::native(arity, move |params| {
+                    let args = bound
+                        .iter()
+                        .cloned()
+                        .map(Ok)
+                        .chain(params)
+                        .collect::<Result<Vec<_>>>()?;
+
+                    f.call(args.into_iter().map(Ok))
+                })))
+            }
+            BuiltinFn::Curry => {
+                let (Some(Ok(Value::Fn(f))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let arity = f.arity().ok_or(BadProgram)?;
+
+                Ok(Value::Fn(curried(f, arity, Vec::new())))
+            }
+            BuiltinFn::Compose => {
+                let functions = params
+                    .map(|param| match param? {
+                        Value::Fn(f) => Ok(f),
+                        _ => Err(BadProgram),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                let arity = functions.last().ok_or(BadProgram)?.arity();
+
+                Ok(Value::Fn(Function::native(arity, move |params| {
+                    let (innermost, rest) = functions.split_last().unwrap();
+
+                    let mut value = innermost.call(params)?;
+
+                    for f in rest.iter().rev() {
+                        value = f.call([Ok(value)].into_iter())?;
+                    }
+
+                    Ok(value)
+                })))
+            }
+            BuiltinFn::Flip => {
+                let (Some(Ok(Value::Fn(f))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Fn(Function::native(f.arity(), move |params| {
+                    let mut args = params.collect::<Result<Vec<_>>>()?;
+
+                    if args.len() < 2 {
+                        return Err(BadProgram);
+                    }
+
+                    args.swap(0, 1);
+
+                    f.call(args.into_iter().map(Ok))
+                })))
+            }
+            BuiltinFn::Arity => {
+                let (Some(Ok(Value::Fn(f))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(match f.arity() {
+                    Some(arity) => Value::Int(Int::from(arity)),
+                    None => Value::Nil,
+                })
+            }
+            // With `detailed: true` functions are told apart by whether they are builtins, user
+            // functions or native functions (the ones returned by builtins like `partial`).
+            BuiltinFn::TypeOf => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let detailed = match option("detailed") {
+                    Some(&Value::Bool(detailed)) => detailed,
+                    Some(_) => return Err(BadProgram),
+                    None => false,
+                };
+
+                Ok(Value::Keyword(Keyword::new(match value? {
+                    Value::Int(_) => "int",
+                    Value::Rational(_) => "rational",
+                    Value::Number(_) => "float",
+                    Value::Bool(_) => "bool",
+                    Value::String(_) => "string",
+                    Value::Char(_) => "char",
+                    Value::Bytes(_) => "bytes",
+                    Value::Symbol(_) => "symbol",
+                    Value::Keyword(_) => "keyword",
+                    Value::Label(_) => "label",
+                    Value::List(_) => "list",
+                    Value::Map(_) => "map",
+                    Value::Set(_) => "set",
+                    Value::Record(_) => "record",
+                    Value::Variant(_) => "variant",
+                    Value::Regex(_) => "regex",
+                    Value::DateTime(_) => "datetime",
+                    Value::Duration(_) => "duration",
+                    Value::Fn(_) if !detailed => "fn",
+                    Value::Fn(Function::Builtin(_)) => "builtin_fn",
+                    Value::Fn(Function::User(_)) => "user_fn",
+                    Value::Fn(Function::Fn(..)) => "native_fn",
+                    Value::Macro(_) => "macro",
+                    Value::Io(_) => "io",
+                    Value::Thunk(_) => "thunk",
+                    Value::Seq(_) => "seq",
+                    Value::Nil => "nil",
+                })))
+            }
+            BuiltinFn::Show => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::String(Rc::new(value?.to_string())))
+            }
+            BuiltinFn::Read => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                parser::read(&string)
+            }
+            // Gives nil if the string isn't a number.
+            BuiltinFn::ParseNumber => {
+                let (Some(Ok(Value::String(string))), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(match parser::read(&string) {
+                    Ok(number @ (Value::Int(_) | Value::Rational(_) | Value::Number(_))) => number,
+                    _ => Value::Nil,
+                })
+            }
+            // Strings go in as they are, everything else gets shown.
+            BuiltinFn::Interpolate => {
+                let mut output = String::new();
+
+                for param in params {
+                    match param? {
+                        Value::String(string) => output.push_str(&string),
+                        value => write!(output, "{value}").unwrap(),
+                    }
+                }
+
+                Ok(Value::String(Rc::new(output)))
+            }
+            BuiltinFn::IsNumber
+            | BuiltinFn::IsInt
+            | BuiltinFn::IsRational
+            | BuiltinFn::IsFloat
+            | BuiltinFn::IsBool
+            | BuiltinFn::IsString
+            | BuiltinFn::IsChar
+            | BuiltinFn::IsBytes
+            | BuiltinFn::IsSymbol
+            | BuiltinFn::IsKeyword
+            | BuiltinFn::IsList
+            | BuiltinFn::IsMap
+            | BuiltinFn::IsSet
+            | BuiltinFn::IsRecord
+            | BuiltinFn::IsVariant
+            | BuiltinFn::IsRegex
+            | BuiltinFn::IsDateTime
+            | BuiltinFn::IsDuration
+            | BuiltinFn::IsFn
+            | BuiltinFn::IsMacro
+            | BuiltinFn::IsIo
+            | BuiltinFn::IsThunk
+            | BuiltinFn::IsSeq
+            | BuiltinFn::IsNil => {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                let value = value?;
+
+                Ok(Value::Bool(match self {
+                    BuiltinFn::IsNumber => {
+                        matches!(value, Value::Int(_) | Value::Rational(_) | Value::Number(_))
+                    }
+                    BuiltinFn::IsInt => matches!(value, Value::Int(_)),
+                    BuiltinFn::IsRational => matches!(value, Value::Rational(_)),
+                    BuiltinFn::IsFloat => matches!(value, Value::Number(_)),
+                    BuiltinFn::IsBool => matches!(value, Value::Bool(_)),
+                    BuiltinFn::IsString => matches!(value, Value::String(_)),
+                    BuiltinFn::IsChar => matches!(value, Value::Char(_)),
+                    BuiltinFn::IsBytes => matches!(value, Value::Bytes(_)),
+                    BuiltinFn::IsSymbol => matches!(value, Value::Symbol(_)),
+                    BuiltinFn::IsKeyword => matches!(value, Value::Keyword(_)),
+                    BuiltinFn::IsList => matches!(value, Value::List(_)),
+                    BuiltinFn::IsMap => matches!(value, Value::Map(_)),
+                    BuiltinFn::IsSet => matches!(value, Value::Set(_)),
+                    BuiltinFn::IsRecord => matches!(value, Value::Record(_)),
+                    BuiltinFn::IsVariant => matches!(value, Value::Variant(_)),
+                    BuiltinFn::IsRegex => matches!(value, Value::Regex(_)),
+                    BuiltinFn::IsDateTime => matches!(value, Value::DateTime(_)),
+                    BuiltinFn::IsDuration => matches!(value, Value::Duration(_)),
+                    BuiltinFn::IsFn => matches!(value, Value::Fn(_)),
+                    BuiltinFn::IsMacro => matches!(value, Value::Macro(_)),
+                    BuiltinFn::IsIo => matches!(value, Value::Io(_)),
+                    BuiltinFn::IsThunk => matches!(value, Value::Thunk(_)),
+                    BuiltinFn::IsSeq => matches!(value, Value::Seq(_)),
+                    BuiltinFn::IsNil => matches!(value, Value::Nil),
+                    _ => unreachable!(),
+                }))
+            }
+            BuiltinFn::Then => {
+                let (Some(Ok(Value::Io(a))), Some(Ok(Value::Io(b))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Io(a.then(b)))
+            }
+            BuiltinFn::Bind => {
+                let (Some(Ok(Value::Io(a))), Some(Ok(Value::Fn(b))), None) =
+                    (params.next(), params.next(), params.next())
+                else {
+                    return Err(BadProgram);
+                };
+
+                a.bind(&b).map(Value::Io)
+            }
+            BuiltinFn::Return => {
+                if params.len() != 1 {
+                    return Err(BadProgram);
+                }
+
+                Ok(Value::Io(Rc::new(Io::Done(params.next().unwrap()?))))
+            }
+            BuiltinFn::Now => {
+                if params.len() != 0 {
+                    return Err(BadProgram);
+                }
+
+                Ok(Value::Io(Rc::new(Io::Now(Function::Builtin(
+                    BuiltinFn::Return,
+                )))))
+            }
+            BuiltinFn::ReadLine => {
+                if params.len() != 0 {
+                    return Err(BadProgram);
+                }
+
+                let read_line = Rc::new(Io::ReadLine(Function::Builtin(BuiltinFn::Return)));
+
+                Ok(Value::Io(match option("prompt") {
+                    Some(Value::String(prompt)) => Rc::new(Io::Print(prompt.clone(), read_line)),
+                    Some(_) => return Err(BadProgram),
+                    None => read_line,
+                }))
+            }
+            BuiltinFn::PrintLine => {
+                let Some(Ok(Value::String(line))) = params.next() else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Io(Rc::new(Io::PrintLine(
+                    line,
+                    Rc::new(Io::Done(Value::Nil)),
+                ))))
+            }
+        }
+    }
+}
+
+fn delimiter(option: Option<&Value>) -> Result<char> {
+    match option {
+        Some(&Value::Char(c)) if !matches!(c, '"' | '\n' | '\r') => Ok(c),
+        Some(_) => Err(BadProgram),
+        None => Ok(','),
+    }
+}
+
+// How many patterns that were given as strings stay compiled.
+const REGEX_CACHE_SIZE: usize = 64;
+
+thread_local! {
+    static REGEXES: RefCell<HashMap<Rc<String>, Rc<Regex>>> = RefCell::new(HashMap::new());
+}
+
+// The regex functions take either a regex or a string. Strings get compiled the first time they
+// are used and then kept around, so calling them in a loop doesn't compile the pattern every time.
+fn compiled(pattern: Value) -> Result<Rc<Regex>> {
+    match pattern {
+        Value::Regex(regex) => Ok(regex),
+        Value::String(pattern) => REGEXES.with_borrow_mut(|regexes| {
+            if let Some(regex) = regexes.get(&pattern) {
+                return Ok(regex.clone());
+            }
+
+            let regex = Rc::new(Regex::new(&pattern).map_err(|_| BadProgram)?);
+
+            // Starting over is simpler than keeping track of which patterns were used last.
+            if regexes.len() >= REGEX_CACHE_SIZE {
+                regexes.clear();
+            }
+
+            regexes.insert(pattern, regex.clone());
+
+            Ok(regex)
+        }),
+        _ => Err(BadProgram),
+    }
+}
+
+// Most functions that only need to go over a list once work with lazy sequences just as well.
+fn values(list: Value) -> Result<Box<dyn Iterator<Item = Result<Value>>>> {
+    Ok(match list {
+        Value::List(list) => Box::new(list.into_iter().map(Ok)),
+        Value::Seq(seq) => Box::new(seq.iter()),
+        _ => return Err(BadProgram),
+    })
+}
+
+// Counts from `start` up (or down) to `end`, which isn't included.
+fn range(start: Value, end: Value, step: Value, direction: Ordering) -> Seq {
+    Seq::new(move || {
+        if number::compare(&start, &end)? != Some(direction) {
+            return Ok(None);
+        }
+
+        let next = number::add(&start, &step)?;
+
+        Ok(Some((start, range(next, end, step, direction))))
+    })
+}
+
+// Collects arguments until there are enough to call `f`. Calling a curried function with more
+// arguments than it still needs is an error, just like with any other function.
+fn curried(f: Function, arity: usize, bound: Vec<Value>) -> Function {
+    Function::native(Some(arity - bound.len()), move |params| {
+        let mut args = bound.clone();
+
+        for param in params {
+            args.push(param?);
+        }
+
+        match args.len().cmp(&arity) {
+            Ordering::Less => Ok(Value::Fn(curried(f.clone(), arity, args))),
+            Ordering::Equal => f.call(args.into_iter().map(Ok)),
+            Ordering::Greater => Err(BadProgram),
+        }
+    })
+}
+
+impl BuiltinMacro {
+    pub fn call(self, scope: &Rc<Scope>, content: &[Value]) -> Result<Value> {
+        match self {
+            BuiltinMacro::Block => eval_block(scope.clone(), content),
+            BuiltinMacro::Do => eval_do_block(scope, content).map(Value::Io),
+            BuiltinMacro::Delay => {
+                let [expr] = content else {
+                    return Err(BadProgram);
+                };
+
+                let scope = scope.clone();
+                let expr = expr.clone();
+
+                Ok(Value::Thunk(Rc::new(Thunk::new(move || {
+                    eval(&scope, &expr)
+                }))))
+            }
+            BuiltinMacro::Fn => {
+                if content.len() < 2 {
+                    return Err(BadProgram);
+                }
+
+                let Value::List(ref list) = content[0] else {
+                    return Err(BadProgram);
+                };
+
+                let mut params: Vec<&'static str> = Vec::new();
+                let mut named: Vec<(&'static str, Value)> = Vec::new();
+
+                let mut list = list.iter();
+
+                while let Some(param) = list.next() {
+                    let (name, default) = match *param {
+                        Value::Symbol(name) => (name, None),
+                        // The default gets evaluated when the function is defined.
+                        Value::Label(name) => {
+                            (name, Some(eval(scope, list.next().ok_or(BadProgram)?)?))
+                        }
+                        _ => return Err(BadProgram),
+                    };
+
+                    // Duplicate parameter names are not allowed.
+                    if params.contains(&name) || named.iter().any(|&(other, _)| other == name) {
+                        return Err(BadProgram);
+                    }
+
+                    match default {
+                        Some(default) => named.push((name, default)),
+                        None => params.push(name),
+                    }
+                }
+
+                Ok(Value::Fn(Function::User(Rc::new(UserFn {
+                    scope: scope.clone(),
+                    params,
+                    named,
+                    content: content[1..].to_vec(),
+                }))))
+            }
+            BuiltinMacro::If => {
+                let [condition, then, otherwise] = content else {
+                    return Err(BadProgram);
+                };
+
+                match eval(scope, condition)? {
+                    Value::Bool(true) => eval(scope, then),
+                    Value::Bool(false) => eval(scope, otherwise),
+                    _ => Err(BadProgram),
+                }
+            }
+            // `[case value [pattern result] ...]` evaluates to the result of the first pattern that
+            // is equal to the value. The patterns get evaluated as well, except for `_`, which
+            // matches anything.
+            BuiltinMacro::Case => {
+                let (value, branches) = content.split_first().ok_or(BadProgram)?;
+
+                let value = eval(scope, value)?;
+
+                for branch in branches {
+                    let Value::List(branch) = branch else {
+                        return Err(BadProgram);
+                    };
+
+                    let [pattern, result] = &*branch.as_slice() else {
+                        return Err(BadProgram);
+                    };
+
+                    if let Value::Symbol("_") = pattern {
+                        return eval(scope, result);
+                    }
+
+                    if eval(scope, pattern)? == value {
+                        return eval(scope, result);
+                    }
+                }
+
+                Err(BadProgram)
             }
         }
     }
@@ -0,0 +1,170 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use crate::{Function, Value, number};
+
+// Structural equality and a total order over all values, so that anything can be compared with `=`,
+// sorted and used as a key. Numbers are compared by their value regardless of their type, so `1`,
+// `1.0` and `2/2` are all the same key. Values that aren't data (functions, I/O and so on) are only
+// equal to themselves.
+
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Nil => 0,
+        Value::Bool(_) => 1,
+        Value::Int(_) | Value::Rational(_) | Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::Char(_) => 4,
+        Value::Bytes(_) => 5,
+        Value::Symbol(_) => 6,
+        Value::Keyword(_) => 7,
+        Value::Label(_) => 8,
+        Value::List(_) => 9,
+        Value::Map(_) => 10,
+        Value::Set(_) => 11,
+        Value::Record(_) => 12,
+        Value::Variant(_) => 13,
+        Value::Regex(_) => 14,
+        Value::DateTime(_) => 15,
+        Value::Duration(_) => 16,
+        Value::Fn(_) => 17,
+        Value::Macro(_) => 18,
+        Value::Io(_) => 19,
+        Value::Thunk(_) => 20,
+        Value::Seq(_) => 21,
+    }
+}
+
+fn identity<T: ?Sized>(rc: &Rc<T>) -> usize {
+    Rc::as_ptr(rc) as *const () as usize
+}
+
+fn function_identity(function: &Function) -> (u8, usize) {
+    match function {
+        Function::Builtin(builtin_fn) => (0, *builtin_fn as usize),
+        Function::User(user_fn) => (1, identity(user_fn)),
+        Function::Fn(_, f) => (2, identity(f)),
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (
+                a @ (Value::Int(_) | Value::Rational(_) | Value::Number(_)),
+                b @ (Value::Int(_) | Value::Rational(_) | Value::Number(_)),
+            ) => number::total_cmp(a, b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Char(a), Value::Char(b)) => a.cmp(b),
+            (Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+            (Value::Symbol(a), Value::Symbol(b)) => a.cmp(b),
+            (Value::Keyword(a), Value::Keyword(b)) => a.cmp(b),
+            (Value::Label(a), Value::Label(b)) => a.cmp(b),
+            (Value::List(a), Value::List(b)) => a.cmp(b),
+            (Value::Map(a), Value::Map(b)) => a.cmp(b),
+            (Value::Set(a), Value::Set(b)) => a.cmp(b),
+            // By the name of the type first, so that the order doesn't depend on where things
+            // happen to be in memory. Only different types with the same name are told apart by
+            // address.
+            (Value::Record(a), Value::Record(b)) => (a.ty().name(), identity(a.ty()))
+                .cmp(&(b.ty().name(), identity(b.ty())))
+                .then_with(|| a.values().cmp(b.values())),
+            (Value::Variant(a), Value::Variant(b)) => (a.ty().name(), identity(a.ty()))
+                .cmp(&(b.ty().name(), identity(b.ty())))
+                .then_with(|| a.tag().cmp(&b.tag()))
+                .then_with(|| a.payload().cmp(b.payload())),
+            // Two regexes are the same if they have the same pattern.
+            (Value::Regex(a), Value::Regex(b)) => a.as_str().cmp(b.as_str()),
+            // Only the moment counts, like for `<` and `>`, so the offset doesn't matter.
+            (Value::DateTime(a), Value::DateTime(b)) => a.cmp(b),
+            (Value::Duration(a), Value::Duration(b)) => a.cmp(b),
+            (Value::Fn(a), Value::Fn(b)) => function_identity(a).cmp(&function_identity(b)),
+            (Value::Macro(a), Value::Macro(b)) => (*a as usize).cmp(&(*b as usize)),
+            (Value::Io(a), Value::Io(b)) => identity(a).cmp(&identity(b)),
+            (Value::Thunk(a), Value::Thunk(b)) => identity(a).cmp(&identity(b)),
+            (Value::Seq(a), Value::Seq(b)) => a.identity().cmp(&b.identity()),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        rank(self).hash(state);
+
+        match self {
+            Value::Nil => {}
+            Value::Bool(b) => b.hash(state),
+            Value::Int(_) | Value::Rational(_) | Value::Number(_) => number::hash(self, state),
+            Value::String(s) => s.hash(state),
+            Value::Char(c) => c.hash(state),
+            Value::Bytes(bytes) => bytes.hash(state),
+            Value::Symbol(name) | Value::Label(name) => name.hash(state),
+            Value::Keyword(keyword) => keyword.hash(state),
+            Value::List(list) => list.hash(state),
+            Value::Map(map) => map.hash(state),
+            Value::Set(set) => set.hash(state),
+            Value::Record(record) => {
+                record.ty().name().hash(state);
+                record.values().hash(state);
+            }
+            Value::Variant(variant) => {
+                variant.ty().name().hash(state);
+                variant.tag().hash(state);
+                variant.payload().hash(state);
+            }
+            Value::Regex(regex) => regex.as_str().hash(state),
+            Value::DateTime(datetime) => datetime.naive_utc().hash(state),
+            Value::Duration(duration) => duration.hash(state),
+            Value::Fn(function) => function_identity(function).hash(state),
+            Value::Macro(builtin_macro) => (*builtin_macro as usize).hash(state),
+            Value::Io(io) => identity(io).hash(state),
+            Value::Thunk(thunk) => identity(thunk).hash(state),
+            Value::Seq(seq) => seq.identity().hash(state),
+        }
+    }
+}
+
+// Whether a value can be used as a key in a map or be part of a set. Only data can, so no functions
+// or I/O.
+pub fn is_key(value: &Value) -> bool {
+    match value {
+        Value::Nil
+        | Value::Bool(_)
+        | Value::Int(_)
+        | Value::Rational(_)
+        | Value::Number(_)
+        | Value::String(_)
+        | Value::Char(_)
+        | Value::Bytes(_)
+        | Value::Symbol(_)
+        | Value::Keyword(_)
+        | Value::Regex(_)
+        | Value::DateTime(_)
+        | Value::Duration(_) => true,
+        Value::List(list) => list.iter().all(is_key),
+        Value::Map(map) => map.iter().all(|(key, value)| is_key(key) && is_key(value)),
+        Value::Set(set) => set.iter().all(is_key),
+        Value::Record(record) => record.values().iter().all(is_key),
+        Value::Variant(variant) => variant.payload().iter().all(is_key),
+        _ => false,
+    }
+}
@@ -0,0 +1,129 @@
+// Reads CSV the way RFC 4180 describes it, except that any character can be the delimiter and lines
+// can also end in just `\n` or `\r`. Fields in double quotes can contain the delimiter, line breaks
+// and double quotes, which are written twice. A line break at the end of the text doesn't start
+// another row. Returns `None` for a quote that isn't closed or something other than a delimiter or
+// a line break right after a closing quote.
+pub fn parse(text: &str, delimiter: char) -> Option<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    if chars.peek().is_none() {
+        return Some(rows);
+    }
+
+    loop {
+        let mut field = String::new();
+
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    '"' => break,
+                    c => field.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != delimiter && c != '\n' && c != '\r') {
+                field.push(c);
+            }
+        }
+
+        row.push(field);
+
+        match chars.next() {
+            Some(c) if c == delimiter => {}
+            Some(c @ ('\r' | '\n')) => {
+                if c == '\r' {
+                    chars.next_if_eq(&'\n');
+                }
+
+                rows.push(std::mem::take(&mut row));
+
+                if chars.peek().is_none() {
+                    return Some(rows);
+                }
+            }
+            None => {
+                rows.push(row);
+                return Some(rows);
+            }
+            Some(_) => return None,
+        }
+    }
+}
+
+// Only quotes fields that need it.
+pub fn format(rows: impl IntoIterator<Item = Vec<String>>, delimiter: char) -> String {
+    let mut output = String::new();
+
+    for row in rows {
+        for (i, field) in row.into_iter().enumerate() {
+            if i > 0 {
+                output.push(delimiter);
+            }
+
+            if field.contains([delimiter, '"', '\n', '\r']) {
+                output.push('"');
+                output.push_str(&field.replace('"', "\"\""));
+                output.push('"');
+            } else {
+                output.push_str(&field);
+            }
+        }
+
+        output.push('\n');
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|field| field.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn quoted_fields() {
+        assert_eq!(
+            parse("a,\"b,c\",\"say \"\"hi\"\"\"\n\"two\nlines\",", ',').unwrap(),
+            rows(&[&["a", "b,c", "say \"hi\""], &["two\nlines", ""]])
+        );
+        assert_eq!(parse("\"open", ','), None);
+        assert_eq!(parse("\"a\"b", ','), None);
+    }
+
+    #[test]
+    fn line_endings() {
+        let expected = rows(&[&["a", "b"], &["c", "d"]]);
+
+        assert_eq!(parse("a,b\r\nc,d\r\n", ',').unwrap(), expected);
+        assert_eq!(parse("a,b\rc,d", ',').unwrap(), expected);
+        assert_eq!(parse("a,b\nc,d\n", ',').unwrap(), expected);
+        assert_eq!(parse("", ',').unwrap(), rows(&[]));
+    }
+
+    #[test]
+    fn round_trips() {
+        let table = rows(&[
+            &["plain", "with;delimiter", "with \"quotes\""],
+            &["line\nbreak", "", "carriage\rreturn"],
+        ]);
+
+        let text = format(table.clone(), ';');
+
+        assert_eq!(
+            text,
+            concat!(
+                "plain;\"with;delimiter\";\"with \"\"quotes\"\"\"\n",
+                "\"line\nbreak\";;\"carriage\rreturn\"\n",
+            )
+        );
+        assert_eq!(parse(&text, ';').unwrap(), table);
+    }
+}
@@ -0,0 +1,118 @@
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// Standard base64 with padding.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(BASE64[(group >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
+
+pub fn base64_decode(base64: &str) -> Option<Vec<u8>> {
+    let base64 = base64.as_bytes();
+
+    if !base64.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut output = Vec::with_capacity(base64.len() / 4 * 3);
+
+    for (n, chunk) in base64.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+
+        // Padding is only allowed at the very end.
+        if padding > 2 || (padding > 0 && n != base64.len() / 4 - 1) {
+            return None;
+        }
+
+        let mut group = 0u32;
+
+        for (i, &c) in chunk[..4 - padding].iter().enumerate() {
+            let value = BASE64.iter().position(|&b| b == c)? as u32;
+            group |= value << (18 - 6 * i);
+        }
+
+        for i in 0..3 - padding {
+            output.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips() {
+        let bytes: Vec<u8> = (0..=255).collect();
+
+        assert_eq!(hex_encode(b"\x00\x0f\xff"), "000fff");
+        assert_eq!(hex_decode(&hex_encode(&bytes)).unwrap(), bytes);
+        assert_eq!(hex_decode("ABcd").unwrap(), [0xab, 0xcd]);
+    }
+
+    #[test]
+    fn invalid_hex() {
+        assert_eq!(hex_decode("abc"), None);
+        assert_eq!(hex_decode("zz"), None);
+        assert_eq!(hex_decode("+1"), None);
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        let cases: [(&[u8], &str); 5] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+        ];
+
+        for (bytes, base64) in cases {
+            assert_eq!(base64_encode(bytes), base64);
+            assert_eq!(base64_decode(base64).unwrap(), bytes);
+        }
+
+        let bytes: Vec<u8> = (0..=255).collect();
+
+        assert_eq!(base64_decode(&base64_encode(&bytes)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn invalid_base64() {
+        assert_eq!(base64_decode("Zm9"), None);
+        assert_eq!(base64_decode("Zg==Zm9v"), None);
+        assert_eq!(base64_decode("Z==="), None);
+        assert_eq!(base64_decode("Zm9-"), None);
+    }
+}
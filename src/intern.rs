@@ -0,0 +1,18 @@
+use std::{cell::RefCell, collections::HashSet};
+
+thread_local! {
+    static NAMES: RefCell<HashSet<&'static str>> = RefCell::new(HashSet::new());
+}
+
+// Returns the one copy of a name that is kept around for good, so names that come up again and
+// again, like the ones of record accessors, only get leaked once.
+pub fn intern(name: &str) -> &'static str {
+    NAMES.with_borrow_mut(|names| match names.get(name) {
+        Some(&name) => name,
+        None => {
+            let name: &'static str = name.to_string().leak();
+            names.insert(name);
+            name
+        }
+    })
+}
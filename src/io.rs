@@ -1,11 +1,15 @@
-use std::rc::Rc;
+use std::{io::Write, rc::Rc};
 
 use crate::{BadProgram, Function, Result, Value};
 
 #[derive(Debug)]
 pub enum Io {
     ReadLine(Function),
+    // The current local time.
+    Now(Function),
     PrintLine(Rc<String>, Rc<Io>),
+    // Like `PrintLine`, but without the newline, for prompts.
+    Print(Rc<String>, Rc<Io>),
     Done(Value),
 }
 
@@ -26,32 +30,34 @@ impl Io {
 
                 next.execute()
             }
+            Io::Now(function) => {
+                let now = Value::DateTime(chrono::Local::now().fixed_offset());
+
+                let Value::Io(next) = function.call([Ok(now)].into_iter())? else {
+                    return Err(BadProgram);
+                };
+
+                next.execute()
+            }
             Io::PrintLine(line, io) => {
                 println!("{line}");
                 io.execute()
             }
+            Io::Print(text, io) => {
+                print!("{text}");
+                std::io::stdout().flush().map_err(|_| BadProgram)?;
+                io.execute()
+            }
             Io::Done(value) => Ok(value.clone()),
         }
     }
 
     pub fn bind(&self, f: &Function) -> Result<Rc<Io>> {
         match self {
-            Io::ReadLine(function) => Ok(Rc::new(Io::ReadLine(Function::Fn(Rc::new({
-                let f = f.clone();
-                let function = function.clone();
-                move |params| {
-                    let (Some(val), None) = (params.next(), params.next()) else {
-                        return Err(BadProgram);
-                    };
-
-                    let Value::Io(io) = f.call([val].into_iter())? else {
-                        return Err(BadProgram);
-                    };
-
-                    io.bind(&function).map(Value::Io)
-                }
-            }))))),
+            Io::ReadLine(function) => Ok(Rc::new(Io::ReadLine(bind_next(function, f)))),
+            Io::Now(function) => Ok(Rc::new(Io::Now(bind_next(function, f)))),
             Io::PrintLine(line, io) => Ok(Rc::new(Io::PrintLine(line.clone(), io.bind(f)?))),
+            Io::Print(text, io) => Ok(Rc::new(Io::Print(text.clone(), io.bind(f)?))),
             Io::Done(value) => {
                 let Value::Io(next) = f.call([Ok(value.clone())].into_iter())? else {
                     return Err(BadProgram);
@@ -64,23 +70,96 @@ impl Io {
 
     pub fn then(&self, other: Rc<Io>) -> Rc<Io> {
         match self {
-            Io::ReadLine(f) => Rc::new(Io::ReadLine(Function::Fn(Rc::new({
-                let f = f.clone();
-                let other = other.clone();
-                move |params| {
-                    let (Some(val), None) = (params.next(), params.next()) else {
-                        return Err(BadProgram);
-                    };
-
-                    let Value::Io(io) = f.call([val].into_iter())? else {
-                        return Err(BadProgram);
-                    };
-
-                    Ok(Value::Io(io.then(other.clone())))
-                }
-            })))),
+            Io::ReadLine(f) => Rc::new(Io::ReadLine(then_next(f, other))),
+            Io::Now(f) => Rc::new(Io::Now(then_next(f, other))),
             Io::PrintLine(line, io) => Rc::new(Io::PrintLine(line.clone(), io.then(other))),
+            Io::Print(text, io) => Rc::new(Io::Print(text.clone(), io.then(other))),
             Io::Done(_) => other,
         }
     }
 }
+
+// The I/O that comes after reading something is a function of what was read, so binding or chaining
+// more I/O has to happen inside that function.
+
+fn bind_next(next: &Function, f: &Function) -> Function {
+    Function::native(Some(1), {
+        let f = f.clone();
+        let next = next.clone();
+        move |params| {
+            let (Some(val), None) = (params.next(), params.next()) else {
+                return Err(BadProgram);
+            };
+
+            let Value::Io(io) = next.call([val].into_iter())? else {
+                return Err(BadProgram);
+            };
+
+            io.bind(&f).map(Value::Io)
+        }
+    })
+}
+
+fn then_next(next: &Function, other: Rc<Io>) -> Function {
+    Function::native(Some(1), {
+        let next = next.clone();
+        move |params| {
+            let (Some(val), None) = (params.next(), params.next()) else {
+                return Err(BadProgram);
+            };
+
+            let Value::Io(io) = next.call([val].into_iter())? else {
+                return Err(BadProgram);
+            };
+
+            Ok(Value::Io(io.then(other.clone())))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A function that appends `suffix` to a string and returns it as I/O.
+    fn append(suffix: &'static str) -> Function {
+        Function::native(Some(1), move |params| {
+            let Some(Ok(Value::String(string))) = params.next() else {
+                return Err(BadProgram);
+            };
+
+            Ok(Value::Io(Rc::new(Io::Done(Value::String(Rc::new(
+                format!("{string}{suffix}"),
+            ))))))
+        })
+    }
+
+    // Feeds a line to I/O that is waiting for one, without going through stdin.
+    fn feed(io: &Io, line: &str) -> Value {
+        let Io::ReadLine(next) = io else {
+            panic!("expected ReadLine, got {io:?}");
+        };
+
+        let Value::Io(io) = next
+            .call([Ok(Value::String(Rc::new(line.to_string())))].into_iter())
+            .unwrap()
+        else {
+            panic!("the continuation didn't return I/O");
+        };
+
+        io.execute().unwrap()
+    }
+
+    #[test]
+    fn bind_on_read_line_runs_in_order() {
+        let read_line = Io::ReadLine(Function::Builtin(crate::BuiltinFn::Return));
+
+        let io = read_line
+            .bind(&append("a"))
+            .unwrap()
+            .bind(&append("b"))
+            .unwrap();
+
+        assert_eq!(feed(&io, "x"), Value::String(Rc::new("xab".to_string())));
+    }
+}
@@ -0,0 +1,187 @@
+use std::{rc::Rc, str::FromStr};
+
+use serde_json::{Map, Number};
+
+use crate::{BadProgram, Result, Value, number};
+
+// Objects become maps with string keys, unless `ordered` is set. Then they become lists of
+// key-value pairs in the order they were written in.
+pub fn parse(text: &str, ordered: bool) -> Result<Value> {
+    from_json(serde_json::from_str(text).map_err(|_| BadProgram)?, ordered)
+}
+
+fn from_json(json: serde_json::Value, ordered: bool) -> Result<Value> {
+    Ok(match json {
+        serde_json::Value::Null => Value::Nil,
+        serde_json::Value::Bool(bool) => Value::Bool(bool),
+        serde_json::Value::Number(number) => from_number(&number)?,
+        serde_json::Value::String(string) => Value::String(Rc::new(string)),
+        serde_json::Value::Array(values) => Value::List(
+            values
+                .into_iter()
+                .map(|value| from_json(value, ordered))
+                .collect::<Result<_>>()?,
+        ),
+        serde_json::Value::Object(object) => {
+            let entries = object
+                .into_iter()
+                .map(|(key, value)| Ok((Value::String(Rc::new(key)), from_json(value, ordered)?)))
+                .collect::<Result<Vec<_>>>()?;
+
+            if ordered {
+                Value::List(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| Value::List([key, value].into_iter().collect()))
+                        .collect(),
+                )
+            } else {
+                Value::Map(entries.into_iter().collect())
+            }
+        }
+    })
+}
+
+// Integers stay exact, no matter how large they are. Everything else is a float, as long as it
+// fits in one, since something like `1e400` would turn into infinity.
+fn from_number(number: &Number) -> Result<Value> {
+    let digits = number.as_str();
+
+    match number::parse_int(digits) {
+        Some(int) if !digits.contains(['.', 'e', 'E']) => Ok(Value::Int(int)),
+        _ => match digits.parse::<f64>() {
+            Ok(float) if float.is_finite() => Ok(Value::Number(float)),
+            _ => Err(BadProgram),
+        },
+    }
+}
+
+pub fn stringify(value: &Value, pretty: bool) -> Result<String> {
+    let json = to_json(value)?;
+
+    if pretty {
+        serde_json::to_string_pretty(&json)
+    } else {
+        serde_json::to_string(&json)
+    }
+    .map_err(|_| BadProgram)
+}
+
+// Keywords and characters turn into strings, rationals into floats and sets into arrays. Map keys
+// have to be strings or keywords, and no two of them can have the same name. Anything that isn't
+// plain data, like functions, I/O or sequences that haven't been forced, can't be turned into JSON
+// at all.
+fn to_json(value: &Value) -> Result<serde_json::Value> {
+    Ok(match value {
+        Value::Nil => serde_json::Value::Null,
+        Value::Bool(bool) => serde_json::Value::Bool(*bool),
+        Value::Int(int) => {
+            serde_json::Value::Number(Number::from_str(&int.to_string()).map_err(|_| BadProgram)?)
+        }
+        Value::Rational(rational) => float(rational.to_f64())?,
+        Value::Number(number) => float(*number)?,
+        Value::String(string) => serde_json::Value::String(string.to_string()),
+        Value::Char(c) => serde_json::Value::String(c.to_string()),
+        Value::Keyword(keyword) => serde_json::Value::String(keyword.name().to_string()),
+        Value::List(list) => {
+            serde_json::Value::Array(list.iter().map(to_json).collect::<Result<_>>()?)
+        }
+        Value::Set(set) => {
+            serde_json::Value::Array(set.iter().map(to_json).collect::<Result<_>>()?)
+        }
+        Value::Map(map) => {
+            let mut object = Map::new();
+
+            for (key, value) in map.iter() {
+                let key = match key {
+                    Value::String(string) => string.to_string(),
+                    Value::Keyword(keyword) => keyword.name().to_string(),
+                    _ => return Err(BadProgram),
+                };
+
+                // Like `"a"` and `:a`, which would otherwise lose one of the values.
+                if object.insert(key, to_json(value)?).is_some() {
+                    return Err(BadProgram);
+                }
+            }
+
+            serde_json::Value::Object(object)
+        }
+        _ => return Err(BadProgram),
+    })
+}
+
+// JSON has no NaN or infinity.
+fn float(float: f64) -> Result<serde_json::Value> {
+    Number::from_f64(float)
+        .map(serde_json::Value::Number)
+        .ok_or(BadProgram)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keyword::Keyword, parser};
+
+    fn round_trip(text: &str) -> String {
+        stringify(&parse(text, false).unwrap(), false).unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        assert_eq!(
+            round_trip(r#"[1,2.5,"a",true,null]"#),
+            r#"[1,2.5,"a",true,null]"#
+        );
+        assert_eq!(
+            round_trip(r#"{"b":{"c":[]},"a":1}"#),
+            r#"{"a":1,"b":{"c":[]}}"#
+        );
+        assert_eq!(
+            round_trip(r#""quote \" and \n\u00e9""#),
+            r#""quote \" and \né""#
+        );
+    }
+
+    #[test]
+    fn big_integers_stay_exact() {
+        let big = "123456789012345678901234567890";
+
+        assert!(matches!(parse(big, false).unwrap(), Value::Int(_)));
+        assert_eq!(round_trip(big), big);
+        assert!(matches!(
+            parse("1e3", false).unwrap(),
+            Value::Number(1000.0)
+        ));
+    }
+
+    #[test]
+    fn ordered_objects_keep_their_order() {
+        let value = parse(r#"{"b":1,"a":2}"#, true).unwrap();
+
+        assert_eq!(value, parser::read(r#"[["b" 1] ["a" 2]]"#).unwrap());
+    }
+
+    #[test]
+    fn values_that_are_not_json() {
+        assert!(parse("[1,", false).is_err());
+        assert!(parse("1e400", false).is_err());
+        assert!(stringify(&Value::Number(f64::NAN), false).is_err());
+        assert!(stringify(&parser::read("{1 2}").unwrap(), false).is_err());
+        assert!(stringify(&parser::read("[x]").unwrap(), false).is_err());
+    }
+
+    #[test]
+    fn keys_that_would_collide() {
+        let map = Value::Map(
+            [
+                (Value::String(Rc::new("a".to_string())), Value::Nil),
+                (Value::Keyword(Keyword::new("a")), Value::Nil),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        assert!(stringify(&map, false).is_err());
+    }
+}
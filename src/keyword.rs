@@ -0,0 +1,58 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+use crate::intern::intern;
+
+// A keyword like `:ok` evaluates to itself. Keywords are interned, so there is only ever one copy
+// of each name and comparing two keywords just compares two pointers.
+#[derive(Copy, Clone)]
+pub struct Keyword(&'static str);
+
+impl Keyword {
+    pub fn new(name: &str) -> Keyword {
+        Keyword(intern(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        self.0
+    }
+}
+
+impl PartialEq for Keyword {
+    fn eq(&self, other: &Keyword) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Keyword {}
+
+// Ordered by name, so sorting keywords is deterministic.
+impl Ord for Keyword {
+    fn cmp(&self, other: &Keyword) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.0.cmp(other.0)
+        }
+    }
+}
+
+impl PartialOrd for Keyword {
+    fn partial_cmp(&self, other: &Keyword) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Keyword {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ptr().hash(state);
+    }
+}
+
+impl std::fmt::Debug for Keyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, ":{}", self.0)
+    }
+}
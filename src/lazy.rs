@@ -0,0 +1,344 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{BadProgram, Function, List, Result, Value};
+
+enum State<T> {
+    Pending(Box<dyn FnOnce() -> Result<T>>),
+    Forcing,
+    Done(T),
+    Failed,
+}
+
+// Since everything is pure it doesn't matter when (or whether) a thunk gets forced, so we can just
+// remember the value the first time around.
+pub struct Thunk<T>(RefCell<State<T>>);
+
+impl<T: Clone> Thunk<T> {
+    pub fn new(f: impl FnOnce() -> Result<T> + 'static) -> Thunk<T> {
+        Thunk(RefCell::new(State::Pending(Box::new(f))))
+    }
+
+    pub fn done(value: T) -> Thunk<T> {
+        Thunk(RefCell::new(State::Done(value)))
+    }
+
+    pub fn force(&self) -> Result<T> {
+        let result = match self.0.replace(State::Forcing) {
+            State::Pending(f) => f(),
+            // A thunk that needs its own value to compute its value.
+            State::Forcing => Err(BadProgram),
+            State::Done(value) => Ok(value),
+            State::Failed => Err(BadProgram),
+        };
+
+        *self.0.borrow_mut() = match &result {
+            Ok(value) => State::Done(value.clone()),
+            Err(_) => State::Failed,
+        };
+
+        result
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Thunk<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &*self.0.borrow() {
+            State::Done(value) => f.debug_tuple("Thunk").field(value).finish(),
+            State::Failed => f.write_str("Thunk(<failed>)"),
+            State::Pending(_) | State::Forcing => f.write_str("Thunk(<pending>)"),
+        }
+    }
+}
+
+// A lazy sequence is a thunk that evaluates to either nothing (the end of the sequence) or the
+// first value and the rest of the sequence.
+#[derive(Clone)]
+pub struct Seq(Rc<Thunk<Option<(Value, Seq)>>>);
+
+impl Seq {
+    pub fn new(f: impl FnOnce() -> Result<Option<(Value, Seq)>> + 'static) -> Seq {
+        Seq(Rc::new(Thunk::new(f)))
+    }
+
+    pub fn empty() -> Seq {
+        Seq(Rc::new(Thunk::done(None)))
+    }
+
+    pub fn cons(head: Value, tail: Seq) -> Seq {
+        Seq(Rc::new(Thunk::done(Some((head, tail)))))
+    }
+
+    pub fn from_list(list: List) -> Seq {
+        Self::from_list_at(list, 0)
+    }
+
+    fn from_list_at(list: List, index: usize) -> Seq {
+        Seq::new(move || {
+            Ok(list
+                .get(index)
+                .cloned()
+                .map(|value| (value, Self::from_list_at(list.clone(), index + 1))))
+        })
+    }
+
+    // Turns anything that can be used as the tail of a sequence into a sequence. Thunks only get
+    // forced once the sequence is.
+    pub fn from_value(value: Value) -> Result<Seq> {
+        Ok(match value {
+            Value::Seq(seq) => seq,
+            Value::List(list) => Seq::from_list(list),
+            Value::Nil => Seq::empty(),
+            Value::Thunk(thunk) => Seq::new(move || Seq::from_value(thunk.force()?)?.next()),
+            _ => return Err(BadProgram),
+        })
+    }
+
+    pub fn next(&self) -> Result<Option<(Value, Seq)>> {
+        self.0.force()
+    }
+
+    pub fn map(&self, f: Function) -> Seq {
+        let seq = self.clone();
+
+        Seq::new(move || {
+            Ok(match seq.next()? {
+                Some((head, tail)) => Some((f.call([Ok(head)].into_iter())?, tail.map(f))),
+                None => None,
+            })
+        })
+    }
+
+    // The same value over and over again.
+    pub fn repeat(value: Value) -> Seq {
+        Seq::new(move || Ok(Some((value.clone(), Seq::repeat(value)))))
+    }
+
+    // `value`, `f(value)`, `f(f(value))` and so on.
+    // `f` only gets called once the rest of the sequence is needed.
+    pub fn iterate(value: Value, f: Function) -> Seq {
+        let tail = {
+            let value = value.clone();
+            Seq::new(move || Seq::iterate(f.call([Ok(value)].into_iter())?, f).next())
+        };
+
+        Seq::cons(value, tail)
+    }
+
+    pub fn take(&self, count: usize) -> Seq {
+        if count == 0 {
+            return Seq::empty();
+        }
+
+        let seq = self.clone();
+
+        Seq::new(move || Ok(seq.next()?.map(|(head, tail)| (head, tail.take(count - 1)))))
+    }
+
+    pub fn skip(&self, count: usize) -> Seq {
+        let seq = self.clone();
+
+        Seq::new(move || {
+            let mut seq = seq;
+
+            for _ in 0..count {
+                match seq.next()? {
+                    Some((_, tail)) => seq = tail,
+                    None => return Ok(None),
+                }
+            }
+
+            seq.next()
+        })
+    }
+
+    // Stops at the first value `f` doesn't return `true` for.
+    pub fn take_while(&self, f: Function) -> Seq {
+        let seq = self.clone();
+
+        Seq::new(move || {
+            let Some((head, tail)) = seq.next()? else {
+                return Ok(None);
+            };
+
+            Ok(f.test(head.clone())?.then(|| (head, tail.take_while(f))))
+        })
+    }
+
+    pub fn filter(&self, f: Function) -> Seq {
+        let seq = self.clone();
+
+        Seq::new(move || {
+            let mut seq = seq;
+
+            // Skipping values in a loop, so that long stretches of them don't recurse.
+            while let Some((head, tail)) = seq.next()? {
+                if f.test(head.clone())? {
+                    return Ok(Some((head, tail.filter(f))));
+                }
+
+                seq = tail;
+            }
+
+            Ok(None)
+        })
+    }
+
+    // Pairs up the values of two sequences until one of them runs out.
+    pub fn zip(&self, other: &Seq) -> Seq {
+        let (a, b) = (self.clone(), other.clone());
+
+        Seq::new(move || {
+            let (Some((a_head, a_tail)), Some((b_head, b_tail))) = (a.next()?, b.next()?) else {
+                return Ok(None);
+            };
+
+            let pair = Value::List([a_head, b_head].into_iter().collect());
+
+            Ok(Some((pair, a_tail.zip(&b_tail))))
+        })
+    }
+
+    pub fn identity(&self) -> usize {
+        Rc::as_ptr(&self.0) as usize
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<Value>> {
+        self.iter().collect()
+    }
+
+    pub fn iter(&self) -> SeqIter {
+        SeqIter(Some(self.clone()))
+    }
+
+    fn take_tail(&mut self) -> Option<Seq> {
+        let thunk = Rc::get_mut(&mut self.0)?;
+
+        match std::mem::replace(thunk.0.get_mut(), State::Failed) {
+            State::Done(Some((_, tail))) => Some(tail),
+            _ => None,
+        }
+    }
+}
+
+// Forces one value after the other. Stops after the first error.
+pub struct SeqIter(Option<Seq>);
+
+impl Iterator for SeqIter {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Result<Value>> {
+        match self.0.take()?.next() {
+            Ok(Some((head, tail))) => {
+                self.0 = Some(tail);
+                Some(Ok(head))
+            }
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+// Dropping a long sequence that has been forced would otherwise recurse once per element and blow
+// the stack.
+impl Drop for Seq {
+    fn drop(&mut self) {
+        let mut next = self.take_tail();
+
+        while let Some(mut seq) = next {
+            next = seq.take_tail();
+        }
+    }
+}
+
+impl std::fmt::Debug for Seq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Seq(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::number::{Int, tests::int};
+
+    // Adds one and counts how often it got called.
+    fn inc(calls: &Rc<Cell<usize>>) -> Function {
+        let calls = calls.clone();
+
+        Function::native(Some(1), move |params| {
+            calls.set(calls.get() + 1);
+
+            let Some(Ok(Value::Int(Int::Small(n)))) = params.next() else {
+                return Err(BadProgram);
+            };
+
+            Ok(int(n + 1))
+        })
+    }
+
+    #[test]
+    fn thunks_are_forced_once() {
+        let calls = Rc::new(Cell::new(0));
+        let thunk = Thunk::new({
+            let calls = calls.clone();
+            move || {
+                calls.set(calls.get() + 1);
+                Ok(int(1))
+            }
+        });
+
+        assert_eq!(calls.get(), 0);
+        assert_eq!(thunk.force().unwrap(), int(1));
+        assert_eq!(thunk.force().unwrap(), int(1));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn iterate_only_calls_f_for_the_tail() {
+        let calls = Rc::new(Cell::new(0));
+        let seq = Seq::iterate(int(0), inc(&calls));
+
+        let (head, tail) = seq.next().unwrap().unwrap();
+
+        assert_eq!(head, int(0));
+        assert_eq!(calls.get(), 0);
+
+        assert_eq!(tail.take(3).to_vec().unwrap(), [int(1), int(2), int(3)]);
+        assert_eq!(calls.get(), 3);
+
+        // Forcing the same values again doesn't call `f` again.
+        assert_eq!(seq.take(4).to_vec().unwrap().len(), 4);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn map_is_lazy() {
+        let calls = Rc::new(Cell::new(0));
+        let seq = Seq::from_list([int(1), int(2), int(3)].into_iter().collect()).map(inc(&calls));
+
+        assert_eq!(calls.get(), 0);
+        assert_eq!(seq.take(2).to_vec().unwrap(), [int(2), int(3)]);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn infinite_sequences() {
+        let calls = Rc::new(Cell::new(0));
+        let naturals = Seq::iterate(int(0), inc(&calls));
+
+        assert_eq!(naturals.skip(5).take(2).to_vec().unwrap(), [int(5), int(6)]);
+        assert_eq!(
+            Seq::repeat(int(7)).take(2).to_vec().unwrap(),
+            [int(7), int(7)]
+        );
+
+        let pairs = naturals
+            .zip(&Seq::from_list([int(9)].into_iter().collect()))
+            .to_vec()
+            .unwrap();
+
+        assert_eq!(pairs, [Value::List([int(0), int(9)].into_iter().collect())]);
+    }
+}
@@ -0,0 +1,106 @@
+use std::borrow::Cow;
+
+use im_rc::{
+    Vector,
+    vector::{ConsumingIter, Iter},
+};
+
+use crate::Value;
+
+// Lists are persistent vectors, so adding something to either end, looking something up or taking
+// the rest of a list don't copy the whole list but share most of it with the original instead.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct List(Vector<Value>);
+
+impl List {
+    pub fn new() -> List {
+        List::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.0.get(index)
+    }
+
+    pub fn first(&self) -> Option<&Value> {
+        self.0.front()
+    }
+
+    pub fn iter(&self) -> Iter<'_, Value> {
+        self.0.iter()
+    }
+
+    // Special forms and statements get matched as slices. Short lists are stored in a single chunk,
+    // so this usually doesn't have to copy anything.
+    pub fn as_slice(&self) -> Cow<'_, [Value]> {
+        match self.0.leaves().next() {
+            None => Cow::Borrowed(&[]),
+            Some(leaf) if leaf.len() == self.len() => Cow::Borrowed(leaf),
+            Some(_) => Cow::Owned(self.iter().cloned().collect()),
+        }
+    }
+
+    pub fn push_front(&self, value: Value) -> List {
+        let mut vector = self.0.clone();
+        vector.push_front(value);
+        List(vector)
+    }
+
+    pub fn push_back(&self, value: Value) -> List {
+        let mut vector = self.0.clone();
+        vector.push_back(value);
+        List(vector)
+    }
+
+    // The first `count` values, or all of them if there aren't that many.
+    pub fn take(&self, count: usize) -> List {
+        if count >= self.len() {
+            self.clone()
+        } else {
+            List(self.0.take(count))
+        }
+    }
+
+    // Everything but the first `count` values, or nothing if there aren't that many.
+    pub fn skip(&self, count: usize) -> List {
+        if count >= self.len() {
+            List::new()
+        } else {
+            List(self.0.skip(count))
+        }
+    }
+}
+
+impl FromIterator<Value> for List {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> List {
+        List(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for List {
+    type Item = Value;
+    type IntoIter = ConsumingIter<Value>;
+
+    fn into_iter(self) -> ConsumingIter<Value> {
+        self.0.into_iter()
+    }
+}
+
+impl From<Vec<Value>> for List {
+    fn from(values: Vec<Value>) -> List {
+        values.into_iter().collect()
+    }
+}
+
+impl std::fmt::Debug for List {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
@@ -1,23 +1,62 @@
 use std::{path::PathBuf, rc::Rc};
 
 use builtins::{BuiltinFn, BuiltinMacro};
+use chrono::{DateTime, FixedOffset, TimeDelta};
 use clap::Parser;
+use im_rc::{OrdMap, OrdSet};
 use io::Io;
+use keyword::Keyword;
+use lazy::{Seq, Thunk};
+use list::List;
+use number::{Int, Rational};
 use parser::parse;
+use regex::Regex;
+use types::{Record, Variant};
 
 mod builtins;
+mod compare;
+mod csv;
+mod encoding;
+mod intern;
 mod io;
+mod json;
+mod keyword;
+mod lazy;
+mod list;
+mod number;
 mod parser;
+mod prelude;
+mod print;
+mod time;
+mod types;
 
 #[derive(Parser)]
 struct Cli {
-    path: PathBuf,
+    #[arg(required_unless_present = "show_prelude")]
+    path: Option<PathBuf>,
+
+    /// Run the program without the definitions from the prelude
+    #[arg(long)]
+    no_prelude: bool,
+
+    /// Print the prelude instead of running a program
+    #[arg(long)]
+    show_prelude: bool,
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    let code = match std::fs::read_to_string(cli.path) {
+    if cli.show_prelude {
+        print!("{}", prelude::SOURCE);
+        return;
+    }
+
+    let Some(path) = cli.path else {
+        return;
+    };
+
+    let code = match std::fs::read_to_string(path) {
         Ok(code) => code,
         Err(e) => {
             println!("{e}");
@@ -25,7 +64,7 @@ fn main() {
         }
     };
 
-    let result = dbg!(parse(&code).and_then(|ast| eval_program(&ast)));
+    let result = dbg!(parse(&code).and_then(|ast| eval_program(&ast, !cli.no_prelude)));
 
     if let Ok(Value::Io(io)) = result {
         dbg!(io.execute()).ok();
@@ -39,21 +78,41 @@ pub type Result<T> = std::result::Result<T, BadProgram>;
 
 #[derive(Clone, Debug)]
 pub enum Value {
+    Int(Int),
+    Rational(Rational),
     Number(f64),
+    Bool(bool),
     String(Rc<String>),
-    Symbol(&'static str), // TODO: interning
-    List(Rc<Vec<Value>>),
+    Char(char),
+    Bytes(Rc<Vec<u8>>),
+    Symbol(&'static str),
+    Keyword(Keyword),
+    // The `name:` in front of a named argument or parameter.
+    Label(&'static str),
+    List(List),
+    Map(OrdMap<Value, Value>),
+    Set(OrdSet<Value>),
+    Record(Rc<Record>),
+    Variant(Rc<Variant>),
+    Regex(Rc<Regex>),
+    DateTime(DateTime<FixedOffset>),
+    Duration(TimeDelta),
     Fn(Function),
     Macro(BuiltinMacro),
     Io(Rc<Io>),
+    Thunk(Rc<Thunk<Value>>),
+    Seq(Seq),
     Nil,
 }
 
+pub type NativeFn = dyn Fn(&mut dyn ExactSizeIterator<Item = Result<Value>>) -> Result<Value>;
+
 #[derive(Clone)]
 pub enum Function {
     Builtin(BuiltinFn),
     User(Rc<UserFn>),
-    Fn(Rc<dyn Fn(&mut dyn ExactSizeIterator<Item = Result<Value>>) -> Result<Value>>),
+    // The arity is `None` for variadic functions.
+    Fn(Option<usize>, Rc<NativeFn>),
 }
 
 impl std::fmt::Debug for Function {
@@ -61,17 +120,49 @@ impl std::fmt::Debug for Function {
         match self {
             Self::Builtin(arg0) => f.debug_tuple("Builtin").field(arg0).finish(),
             Self::User(arg0) => f.debug_tuple("User").field(arg0).finish(),
-            Self::Fn(_) => f.debug_tuple("Fn").finish(),
+            Self::Fn(arity, _) => f.debug_tuple("Fn").field(arity).finish(),
         }
     }
 }
 
 impl Function {
-    pub fn call(&self, mut params: impl ExactSizeIterator<Item = Result<Value>>) -> Result<Value> {
+    pub fn native(
+        arity: Option<usize>,
+        f: impl Fn(&mut dyn ExactSizeIterator<Item = Result<Value>>) -> Result<Value> + 'static,
+    ) -> Function {
+        Function::Fn(arity, Rc::new(f))
+    }
+
+    pub fn arity(&self) -> Option<usize> {
+        match self {
+            Function::Builtin(builtin_fn) => builtin_fn.arity(),
+            Function::User(user_fn) => Some(user_fn.params.len()),
+            Function::Fn(arity, _) => *arity,
+        }
+    }
+
+    pub fn call(&self, params: impl ExactSizeIterator<Item = Result<Value>>) -> Result<Value> {
+        self.call_named(params, &[])
+    }
+
+    // Calls a predicate, which has to return a boolean.
+    pub fn test(&self, value: Value) -> Result<bool> {
+        match self.call([Ok(value)].into_iter())? {
+            Value::Bool(result) => Ok(result),
+            _ => Err(BadProgram),
+        }
+    }
+
+    pub fn call_named(
+        &self,
+        mut params: impl ExactSizeIterator<Item = Result<Value>>,
+        named: &[(&'static str, Value)],
+    ) -> Result<Value> {
         match self {
-            Function::Builtin(builtin_fn) => builtin_fn.call(params),
-            Function::User(user_fn) => user_fn.call(params),
-            Function::Fn(f) => f(&mut params),
+            Function::Builtin(builtin_fn) => builtin_fn.call(params, named),
+            Function::User(user_fn) => user_fn.call(params, named),
+            Function::Fn(..) if !named.is_empty() => Err(BadProgram),
+            Function::Fn(_, f) => f(&mut params),
         }
     }
 }
@@ -79,30 +170,43 @@ impl Function {
 #[derive(Debug)]
 pub struct UserFn {
     scope: Rc<Scope>,
-    params: Rc<Vec<Value>>,
+    params: Vec<&'static str>,
+    // Named parameters along with their default values.
+    named: Vec<(&'static str, Value)>,
     content: Vec<Value>,
 }
 
 impl UserFn {
-    pub fn call(&self, params: impl ExactSizeIterator<Item = Result<Value>>) -> Result<Value> {
+    pub fn call(
+        &self,
+        params: impl ExactSizeIterator<Item = Result<Value>>,
+        named: &[(&'static str, Value)],
+    ) -> Result<Value> {
         if self.params.len() != params.len() {
             return Err(BadProgram);
         }
 
+        for (i, (name, _)) in named.iter().enumerate() {
+            if !self.named.iter().any(|(other, _)| other == name)
+                || named[..i].iter().any(|(other, _)| other == name)
+            {
+                return Err(BadProgram);
+            }
+        }
+
         let mut scope = self.scope.clone();
 
-        let mut params_def = self.params.iter();
-
-        for param in params {
-            scope = scope.with(
-                match params_def.next() {
-                    Some(Value::Symbol(name)) => name,
-                    // The lenth of params_def gets checked above and at function definition
-                    // only symbols are allowed.
-                    _ => unreachable!(),
-                },
-                param?,
-            );
+        for (name, param) in self.params.iter().zip(params) {
+            scope = scope.with(name, param?);
+        }
+
+        for (name, default) in &self.named {
+            let value = named
+                .iter()
+                .find(|(other, _)| other == name)
+                .map_or(default, |(_, value)| value);
+
+            scope = scope.with(name, value.clone());
         }
 
         eval_block(scope.clone(), &self.content)
@@ -151,24 +255,58 @@ impl Scope {
     }
 }
 
-fn eval_program(content: &Value) -> Result<Value> {
-    let root_scope = Rc::new(Scope::Empty);
+fn eval_program(content: &Value, prelude: bool) -> Result<Value> {
+    let root_scope = if prelude {
+        prelude::scope()?
+    } else {
+        Rc::new(Scope::Empty)
+    };
 
     eval(&root_scope, content)
 }
 
 fn eval(scope: &Rc<Scope>, input: &Value) -> Result<Value> {
     match input {
-        v @ (Value::Number(_) | Value::String(_)) => Ok(v.clone()),
-        Value::List(values) => {
-            if let [callable, ..] = values.as_slice() {
-                let callable = eval(scope, callable)?;
-                call(scope, &callable, &values[1..])
-            } else {
-                Err(BadProgram)
+        v @ (Value::Int(_)
+        | Value::Rational(_)
+        | Value::Number(_)
+        | Value::String(_)
+        | Value::Char(_)
+        | Value::Bytes(_)
+        | Value::Keyword(_)
+        // Only the parser puts functions into code, for things like interpolated strings.
+        | Value::Fn(_)) => Ok(v.clone()),
+        Value::List(list) => {
+            let callable = eval(scope, list.first().ok_or(BadProgram)?)?;
+            call(scope, &callable, list)
+        }
+        // Keys that are different expressions can still evaluate to the same value, like `[+ 1 1]`
+        // and `2`. That's an error just like writing the same key twice.
+        Value::Map(map) => {
+            let mut result = OrdMap::new();
+
+            for (key, value) in map.iter() {
+                let key = eval(scope, key)?;
+
+                if !compare::is_key(&key) || result.contains_key(&key) {
+                    return Err(BadProgram);
+                }
+
+                result.insert(key, eval(scope, value)?);
             }
+
+            Ok(Value::Map(result))
         }
-        Value::Symbol(name) => scope.resolve(name).map(Clone::clone),
+        Value::Set(set) => set
+            .iter()
+            .map(|value| {
+                let value = eval(scope, value)?;
+
+                compare::is_key(&value).then_some(value).ok_or(BadProgram)
+            })
+            .collect::<Result<_>>()
+            .map(Value::Set),
+        Value::Symbol(name) => scope.resolve(name).cloned(),
         _ => Err(BadProgram),
     }
 }
@@ -179,42 +317,83 @@ fn eval_block(mut scope: Rc<Scope>, content: &[Value]) -> Result<Value> {
     };
 
     for statement in statements {
-        if let &Value::List(ref list) = statement
-            && let [Value::Symbol("let"), Value::Symbol(name), expr] = list.as_slice()
-        {
-            let value = eval(&scope, expr)?;
-
-            scope = scope.with(name, value);
-        } else {
-            return Err(BadProgram);
-        }
+        scope = eval_statement(&scope, statement)?;
     }
 
     eval(&scope, last)
 }
 
+// A `let`, `record` or `type` in a block, which gives the scope for everything after it.
+fn eval_statement(scope: &Rc<Scope>, statement: &Value) -> Result<Rc<Scope>> {
+    if let Value::List(list) = statement
+        && let [Value::Symbol("let"), Value::Symbol(name), expr] = &*list.as_slice()
+    {
+        let value = eval(scope, expr)?;
+
+        Ok(scope.clone().with(name, value))
+    } else if let Value::List(list) = statement
+        && let [
+            Value::Symbol("record"),
+            Value::Symbol(name),
+            Value::List(fields),
+        ] = &*list.as_slice()
+    {
+        types::define_record(scope, name, &fields.as_slice())
+    } else if let Value::List(list) = statement
+        && let [Value::Symbol("type"), Value::Symbol(name), variants @ ..] = &*list.as_slice()
+    {
+        types::define_type(scope, name, variants)
+    } else {
+        Err(BadProgram)
+    }
+}
+
 // A purely syntactic transformation would also work here. But what is this? LISP?
 fn eval_do_block(scope: &Rc<Scope>, content: &[Value]) -> Result<Rc<Io>> {
     let (first, rest) = content.split_first().ok_or(BadProgram)?;
 
-    match if let Value::List(list) = first {
-        Some(list.as_slice())
-    } else {
-        None
-    } {
+    let list = match first {
+        Value::List(list) => Some(list.as_slice()),
+        _ => None,
+    };
+
+    match list.as_deref() {
         Some([Value::Symbol("let"), Value::Symbol(name), expr]) => {
-            if rest.len() < 1 {
+            if rest.is_empty() {
                 return Err(BadProgram);
             }
 
             let value = eval(scope, expr)?;
 
-            let scope = scope.clone().with(*name, value);
+            let scope = scope.clone().with(name, value);
 
             eval_do_block(&scope, rest)
         }
+        Some(
+            [
+                Value::Symbol("record"),
+                Value::Symbol(name),
+                Value::List(fields),
+            ],
+        ) => {
+            if rest.is_empty() {
+                return Err(BadProgram);
+            }
+
+            eval_do_block(
+                &types::define_record(scope, name, &fields.as_slice())?,
+                rest,
+            )
+        }
+        Some([Value::Symbol("type"), Value::Symbol(name), variants @ ..]) => {
+            if rest.is_empty() {
+                return Err(BadProgram);
+            }
+
+            eval_do_block(&types::define_type(scope, name, variants)?, rest)
+        }
         Some([Value::Symbol("use"), Value::Symbol(name), expr]) => {
-            if rest.len() < 1 {
+            if rest.is_empty() {
                 return Err(BadProgram);
             }
 
@@ -224,7 +403,7 @@ fn eval_do_block(scope: &Rc<Scope>, content: &[Value]) -> Result<Rc<Io>> {
                 return Err(BadProgram);
             };
 
-            io.bind(&Function::Fn(Rc::new({
+            io.bind(&Function::native(Some(1), {
                 let name = *name;
                 let scope = scope.clone();
 
@@ -245,7 +424,7 @@ fn eval_do_block(scope: &Rc<Scope>, content: &[Value]) -> Result<Rc<Io>> {
 
                     Ok(Value::Io(io))
                 }
-            })))
+            }))
         }
         _ => {
             let value = eval(scope, first)?;
@@ -254,7 +433,7 @@ fn eval_do_block(scope: &Rc<Scope>, content: &[Value]) -> Result<Rc<Io>> {
                 return Err(BadProgram);
             };
 
-            if rest.len() >= 1 {
+            if !rest.is_empty() {
                 Ok(io.then(eval_do_block(scope, rest)?))
             } else {
                 Ok(io)
@@ -263,10 +442,80 @@ fn eval_do_block(scope: &Rc<Scope>, content: &[Value]) -> Result<Rc<Io>> {
     }
 }
 
-fn call(scope: &Rc<Scope>, callable: &Value, params: &[Value]) -> Result<Value> {
+// Calls what the first value of a code list evaluated to with the rest of the list.
+fn call(scope: &Rc<Scope>, callable: &Value, list: &List) -> Result<Value> {
     match callable {
-        Value::Macro(builtin_macro) => builtin_macro.call(scope, params),
-        Value::Fn(function) => function.call(params.iter().map(|param| eval(scope, param))),
+        Value::Macro(builtin_macro) => builtin_macro.call(scope, &list.as_slice()[1..]),
+        Value::Fn(function) => {
+            let mut positional = Vec::with_capacity(list.len() - 1);
+            let mut named = Vec::new();
+
+            let mut params = list.iter().skip(1);
+
+            while let Some(param) = params.next() {
+                if let Value::Label(name) = param {
+                    named.push((*name, eval(scope, params.next().ok_or(BadProgram)?)?));
+                } else {
+                    positional.push(param);
+                }
+            }
+
+            function.call_named(
+                positional.into_iter().map(|param| eval(scope, param)),
+                &named,
+            )
+        }
         _ => Err(BadProgram),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn run(code: &str) -> Result<Value> {
+        eval_program(&parse(code)?, true)
+    }
+
+    #[test]
+    fn map_literals_reject_duplicate_keys() {
+        assert!(run(r#"{"a" 1 "a" 2}"#).is_err());
+        assert!(run("{[+ 1 1] 1 2 2}").is_err());
+
+        let Ok(Value::Map(map)) = run("{[+ 1 1] 1 3 2}") else {
+            panic!("expected a map");
+        };
+
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn strings_count_characters_bytes_and_graphemes() {
+        let count = |function| run(&format!(r#"[{function} "héllo 👍🏽"]"#)).unwrap();
+
+        assert_eq!(count("length"), Value::Int(Int::Small(8)));
+        assert_eq!(count("byte_length"), Value::Int(Int::Small(15)));
+        assert_eq!(count("grapheme_length"), Value::Int(Int::Small(7)));
+        assert_eq!(run(r#"[char_at "héllo" 1]"#).unwrap(), Value::Char('é'));
+        assert!(run(r#"[char_at "héllo" 5]"#).is_err());
+    }
+
+    #[test]
+    fn csv_headers_and_columns() {
+        assert!(run(r#"[csv_parse "a,a\n1,2" header: true]"#).is_err());
+        assert!(run(r#"[csv_format [list {"b" 1 "a" 2}]]"#).is_err());
+        assert_eq!(
+            run(r#"[csv_format [list {"b" 1 "a" 2}] columns: [list "b" "a"]]"#).unwrap(),
+            Value::String(Rc::new("b,a\n1,2\n".to_string()))
+        );
+        assert!(run(r#"[csv_format [list [list 1 2] [list 3]] columns: [list "a" "b"]]"#).is_err());
+    }
+
+    #[test]
+    fn interpolated_strings_ignore_other_bindings_of_interpolate() {
+        assert_eq!(
+            run(r#"[block [let interpolate 5] [let x 1] f"x={x}"]"#).unwrap(),
+            Value::String(Rc::new("x=1".to_string()))
+        );
+    }
+}
@@ -0,0 +1,559 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Pow, Signed, ToPrimitive, Zero};
+
+use crate::{BadProgram, Result, Value};
+
+// Integers stay machine-sized until they overflow, at which point they get promoted to a bignum.
+// Results that fit in an `i64` again always get demoted, so two equal integers always have the same
+// representation.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Int {
+    Small(i64),
+    Big(Rc<BigInt>),
+}
+
+impl Int {
+    pub fn from_big(big: BigInt) -> Int {
+        match big.to_i64() {
+            Some(small) => Int::Small(small),
+            None => Int::Big(Rc::new(big)),
+        }
+    }
+
+    pub fn to_big(&self) -> BigInt {
+        match self {
+            Int::Small(small) => BigInt::from(*small),
+            Int::Big(big) => (**big).clone(),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Int::Small(small) => *small as f64,
+            Int::Big(big) => big.to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    pub fn to_usize(&self) -> Option<usize> {
+        match self {
+            Int::Small(small) => usize::try_from(*small).ok(),
+            Int::Big(_) => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Int::Small(0))
+    }
+
+    fn op(
+        &self,
+        other: &Int,
+        small: impl Fn(i64, i64) -> Option<i64>,
+        big: impl Fn(BigInt, BigInt) -> BigInt,
+    ) -> Int {
+        if let (Int::Small(a), Int::Small(b)) = (self, other)
+            && let Some(result) = small(*a, *b)
+        {
+            Int::Small(result)
+        } else {
+            Int::from_big(big(self.to_big(), other.to_big()))
+        }
+    }
+
+    pub fn add(&self, other: &Int) -> Int {
+        self.op(other, i64::checked_add, |a, b| a + b)
+    }
+
+    pub fn sub(&self, other: &Int) -> Int {
+        self.op(other, i64::checked_sub, |a, b| a - b)
+    }
+
+    pub fn mul(&self, other: &Int) -> Int {
+        self.op(other, i64::checked_mul, |a, b| a * b)
+    }
+
+    // Division truncating towards zero. `None` when dividing by zero.
+    pub fn quot(&self, other: &Int) -> Option<Int> {
+        (!other.is_zero()).then(|| self.op(other, i64::checked_div, |a, b| a / b))
+    }
+
+    // The remainder of `quot`, which has the same sign as `self`.
+    pub fn rem(&self, other: &Int) -> Option<Int> {
+        (!other.is_zero()).then(|| self.op(other, i64::checked_rem, |a, b| a % b))
+    }
+}
+
+impl From<usize> for Int {
+    fn from(value: usize) -> Int {
+        match i64::try_from(value) {
+            Ok(small) => Int::Small(small),
+            Err(_) => Int::Big(Rc::new(BigInt::from(value))),
+        }
+    }
+}
+
+impl Ord for Int {
+    fn cmp(&self, other: &Int) -> Ordering {
+        match (self, other) {
+            (Int::Small(a), Int::Small(b)) => a.cmp(b),
+            _ => self.to_big().cmp(&other.to_big()),
+        }
+    }
+}
+
+impl PartialOrd for Int {
+    fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::fmt::Display for Int {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Int::Small(small) => write!(f, "{small}"),
+            Int::Big(big) => write!(f, "{big}"),
+        }
+    }
+}
+
+impl std::fmt::Debug for Int {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+// Always in lowest terms and never with a denominator of one, those are integers.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Rational(Rc<BigRational>);
+
+impl Rational {
+    pub fn to_big(&self) -> BigRational {
+        (*self.0).clone()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64().unwrap_or(f64::NAN)
+    }
+
+    pub fn numerator(&self) -> Int {
+        Int::from_big(self.0.numer().clone())
+    }
+
+    pub fn denominator(&self) -> Int {
+        Int::from_big(self.0.denom().clone())
+    }
+}
+
+impl std::fmt::Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::fmt::Debug for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+// Turns the result of a calculation into the simplest value that can represent it.
+pub fn rational(value: BigRational) -> Value {
+    if value.is_integer() {
+        Value::Int(Int::from_big(value.to_integer()))
+    } else {
+        Value::Rational(Rational(Rc::new(value)))
+    }
+}
+
+// The numeric tower: integers are rationals and rationals are exact, floats are not. Operands get
+// converted to the least exact type among them, and so does the result. So an integer and a
+// rational give a rational, but as soon as one of the operands is a float the result is a float as
+// well.
+enum Pair {
+    Int(Int, Int),
+    Rational(BigRational, BigRational),
+    Float(f64, f64),
+}
+
+fn promote(a: &Value, b: &Value) -> Result<Pair> {
+    Ok(match (a, b) {
+        (Value::Int(a), Value::Int(b)) => Pair::Int(a.clone(), b.clone()),
+
+        (Value::Int(a), Value::Rational(b)) => Pair::Rational(a.to_big().into(), b.to_big()),
+        (Value::Rational(a), Value::Int(b)) => Pair::Rational(a.to_big(), b.to_big().into()),
+        (Value::Rational(a), Value::Rational(b)) => Pair::Rational(a.to_big(), b.to_big()),
+
+        (Value::Int(a), &Value::Number(b)) => Pair::Float(a.to_f64(), b),
+        (&Value::Number(a), Value::Int(b)) => Pair::Float(a, b.to_f64()),
+        (Value::Rational(a), &Value::Number(b)) => Pair::Float(a.to_f64(), b),
+        (&Value::Number(a), Value::Rational(b)) => Pair::Float(a, b.to_f64()),
+        (&Value::Number(a), &Value::Number(b)) => Pair::Float(a, b),
+
+        _ => return Err(BadProgram),
+    })
+}
+
+pub fn to_f64(value: &Value) -> Result<f64> {
+    Ok(match value {
+        Value::Int(int) => int.to_f64(),
+        Value::Rational(rational) => rational.to_f64(),
+        &Value::Number(float) => float,
+        _ => return Err(BadProgram),
+    })
+}
+
+pub fn add(a: &Value, b: &Value) -> Result<Value> {
+    Ok(match promote(a, b)? {
+        Pair::Int(a, b) => Value::Int(a.add(&b)),
+        Pair::Rational(a, b) => rational(a + b),
+        Pair::Float(a, b) => Value::Number(a + b),
+    })
+}
+
+pub fn sub(a: &Value, b: &Value) -> Result<Value> {
+    Ok(match promote(a, b)? {
+        Pair::Int(a, b) => Value::Int(a.sub(&b)),
+        Pair::Rational(a, b) => rational(a - b),
+        Pair::Float(a, b) => Value::Number(a - b),
+    })
+}
+
+pub fn mul(a: &Value, b: &Value) -> Result<Value> {
+    Ok(match promote(a, b)? {
+        Pair::Int(a, b) => Value::Int(a.mul(&b)),
+        Pair::Rational(a, b) => rational(a * b),
+        Pair::Float(a, b) => Value::Number(a * b),
+    })
+}
+
+// Exact division by zero is an error, float division by zero gives infinity or NaN.
+pub fn div(a: &Value, b: &Value) -> Result<Value> {
+    Ok(match promote(a, b)? {
+        Pair::Int(_, b) if b.is_zero() => return Err(BadProgram),
+        Pair::Int(a, b) => rational(BigRational::new(a.to_big(), b.to_big())),
+        Pair::Rational(_, b) if b.is_zero() => return Err(BadProgram),
+        Pair::Rational(a, b) => rational(a / b),
+        Pair::Float(a, b) => Value::Number(a / b),
+    })
+}
+
+// The remainder of flooring division, which has the same sign as `b` (unlike `rem`).
+pub fn modulo(a: &Value, b: &Value) -> Result<Value> {
+    Ok(match promote(a, b)? {
+        Pair::Int(_, b) if b.is_zero() => return Err(BadProgram),
+        Pair::Int(a, b) => {
+            let rem = a.rem(&b).unwrap();
+            let zero = Int::Small(0);
+
+            Value::Int(if !rem.is_zero() && (rem < zero) != (b < zero) {
+                rem.add(&b)
+            } else {
+                rem
+            })
+        }
+        Pair::Rational(_, b) if b.is_zero() => return Err(BadProgram),
+        Pair::Rational(a, b) => rational(&a - &b * (&a / &b).floor()),
+        Pair::Float(a, b) => {
+            let rem = a % b;
+
+            Value::Number(if rem != 0.0 && (rem < 0.0) != (b < 0.0) {
+                rem + b
+            } else {
+                rem
+            })
+        }
+    })
+}
+
+// Exact results larger than this many bits would take too long and too much memory to compute.
+const MAX_EXACT_POW_BITS: u64 = 1 << 20;
+
+// Exact numbers raised to an integer power stay exact, unless the result would be huge. Everything
+// else turns into a float.
+pub fn pow(base: &Value, exponent: &Value) -> Result<Value> {
+    let exact = match base {
+        Value::Int(int) => Some(BigRational::from(int.to_big())),
+        Value::Rational(rational) => Some(rational.to_big()),
+        _ => None,
+    };
+
+    Ok(match (exact, exponent) {
+        (Some(base), Value::Int(Int::Small(exponent)))
+            if (base.numer().bits() + base.denom().bits())
+                .saturating_sub(2)
+                .saturating_mul(exponent.unsigned_abs())
+                <= MAX_EXACT_POW_BITS =>
+        {
+            if base.is_zero() && *exponent < 0 {
+                return Err(BadProgram);
+            }
+
+            rational(Pow::pow(base, exponent))
+        }
+        _ => Value::Number(to_f64(base)?.powf(to_f64(exponent)?)),
+    })
+}
+
+// The square root of a perfect square (or a rational made of two of them) is exact.
+pub fn sqrt(value: &Value) -> Result<Value> {
+    let exact = match value {
+        Value::Int(int) => Some(BigRational::from(int.to_big())),
+        Value::Rational(rational) => Some(rational.to_big()),
+        _ => None,
+    };
+
+    if let Some(exact) = exact
+        && !exact.is_negative()
+    {
+        let (numerator, denominator) = (exact.numer().sqrt(), exact.denom().sqrt());
+
+        if &numerator * &numerator == *exact.numer()
+            && &denominator * &denominator == *exact.denom()
+        {
+            return Ok(rational(BigRational::new(numerator, denominator)));
+        }
+    }
+
+    to_f64(value).map(|float| Value::Number(float.sqrt()))
+}
+
+pub fn abs(value: &Value) -> Result<Value> {
+    Ok(match value {
+        &Value::Number(float) => Value::Number(float.abs()),
+        _ if compare(value, &Value::Int(Int::Small(0)))? == Some(Ordering::Less) => {
+            sub(&Value::Int(Int::Small(0)), value)?
+        }
+        _ => value.clone(),
+    })
+}
+
+#[derive(Clone, Copy)]
+pub enum Rounding {
+    Floor,
+    Ceil,
+    // Halfway cases round away from zero.
+    Round,
+    Truncate,
+}
+
+// Rationals get rounded to integers, but floats stay floats.
+pub fn round(value: &Value, rounding: Rounding) -> Result<Value> {
+    Ok(match value {
+        Value::Int(_) => value.clone(),
+        Value::Rational(value) => {
+            let value = value.to_big();
+
+            rational(match rounding {
+                Rounding::Floor => value.floor(),
+                Rounding::Ceil => value.ceil(),
+                Rounding::Round => value.round(),
+                Rounding::Truncate => value.trunc(),
+            })
+        }
+        &Value::Number(value) => Value::Number(match rounding {
+            Rounding::Floor => value.floor(),
+            Rounding::Ceil => value.ceil(),
+            Rounding::Round => value.round(),
+            Rounding::Truncate => value.trunc(),
+        }),
+        _ => return Err(BadProgram),
+    })
+}
+
+// The exact value of any number. Every finite float is a rational number, so integers, rationals
+// and floats can all be compared without losing precision.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Exact {
+    NegativeInfinity,
+    Finite(BigRational),
+    Infinity,
+    NaN,
+}
+
+fn exact(value: &Value) -> Result<Exact> {
+    Ok(match value {
+        Value::Int(int) => Exact::Finite(int.to_big().into()),
+        Value::Rational(rational) => Exact::Finite(rational.to_big()),
+        &Value::Number(float) if float.is_nan() => Exact::NaN,
+        &Value::Number(f64::INFINITY) => Exact::Infinity,
+        &Value::Number(f64::NEG_INFINITY) => Exact::NegativeInfinity,
+        &Value::Number(float) => Exact::Finite(BigRational::from_float(float).unwrap()),
+        _ => return Err(BadProgram),
+    })
+}
+
+// `None` if one of the numbers is NaN.
+pub fn compare(a: &Value, b: &Value) -> Result<Option<Ordering>> {
+    let (a, b) = (exact(a)?, exact(b)?);
+
+    Ok((a != Exact::NaN && b != Exact::NaN).then(|| a.cmp(&b)))
+}
+
+// A total order for numbers, where NaN is equal to itself and larger than any other number. Both
+// values have to be numbers.
+pub fn total_cmp(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => a
+            .partial_cmp(b)
+            .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan())),
+        _ => exact(a).unwrap().cmp(&exact(b).unwrap()),
+    }
+}
+
+pub fn hash(value: &Value, state: &mut impl Hasher) {
+    exact(value).unwrap().hash(state);
+}
+
+// Parses something like `1/3`.
+pub fn parse_rational(numerator: &str, denominator: &str) -> Option<Value> {
+    let numerator = parse_int(numerator)?.to_big();
+    let denominator = parse_int(denominator)?.to_big();
+
+    (!denominator.is_zero()).then(|| rational(BigRational::new(numerator, denominator)))
+}
+
+pub fn parse_int(digits: &str) -> Option<Int> {
+    match digits.parse::<i64>() {
+        Ok(small) => Some(Int::Small(small)),
+        Err(_) => digits.parse::<BigInt>().ok().map(Int::from_big),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn int(n: i64) -> Value {
+        Value::Int(Int::Small(n))
+    }
+
+    // A rational as a fraction like `1/3`.
+    fn fraction(value: &Value) -> String {
+        match value {
+            Value::Rational(rational) => format!("{rational:?}"),
+            value => panic!("expected a rational, got {value:?}"),
+        }
+    }
+
+    #[test]
+    fn ints_promote_to_bignums_and_back() {
+        let big = mul(&int(i64::MAX), &int(2)).unwrap();
+
+        assert!(matches!(
+            &big,
+            Value::Int(int) if Some(int) == parse_int("18446744073709551614").as_ref()
+        ));
+
+        let small = sub(&big, &int(i64::MAX)).unwrap();
+
+        assert!(matches!(small, Value::Int(Int::Small(i64::MAX))));
+    }
+
+    #[test]
+    fn floats_win_when_mixing() {
+        assert!(matches!(
+            add(&int(1), &int(2)).unwrap(),
+            Value::Int(Int::Small(3))
+        ));
+        assert!(matches!(
+            add(&int(1), &Value::Number(2.5)).unwrap(),
+            Value::Number(3.5)
+        ));
+        assert!(matches!(
+            mul(&Value::Number(0.5), &int(4)).unwrap(),
+            Value::Number(2.0)
+        ));
+        assert!(add(&int(1), &Value::Nil).is_err());
+    }
+
+    #[test]
+    fn integer_division() {
+        assert_eq!(Int::Small(-7).quot(&Int::Small(2)), Some(Int::Small(-3)));
+        assert_eq!(Int::Small(-7).rem(&Int::Small(2)), Some(Int::Small(-1)));
+        assert_eq!(Int::Small(1).quot(&Int::Small(0)), None);
+        assert_eq!(
+            Int::Small(i64::MIN).quot(&Int::Small(-1)),
+            parse_int("9223372036854775808")
+        );
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(
+            compare(&int(1), &Value::Number(1.0)).unwrap(),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            compare(&int(2), &Value::Number(1.5)).unwrap(),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(compare(&Value::Number(f64::NAN), &int(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn rationals_are_normalized() {
+        let third = div(&int(2), &int(6)).unwrap();
+
+        assert_eq!(fraction(&third), "1/3");
+        assert_eq!(fraction(&div(&int(-2), &int(-6)).unwrap()), "1/3");
+        assert_eq!(fraction(&div(&int(2), &int(-6)).unwrap()), "-1/3");
+
+        // Whole results turn back into integers.
+        assert!(matches!(
+            div(&int(8), &int(2)).unwrap(),
+            Value::Int(Int::Small(4))
+        ));
+        assert!(matches!(
+            add(&third, &div(&int(2), &int(3)).unwrap()).unwrap(),
+            Value::Int(Int::Small(1))
+        ));
+    }
+
+    #[test]
+    fn rationals_mix_with_other_numbers() {
+        let half = parse_rational("1", "2").unwrap();
+
+        assert_eq!(fraction(&add(&half, &int(1)).unwrap()), "3/2");
+        assert!(matches!(
+            add(&half, &Value::Number(1.0)).unwrap(),
+            Value::Number(1.5)
+        ));
+        assert_eq!(
+            compare(&half, &Value::Number(0.5)).unwrap(),
+            Some(Ordering::Equal)
+        );
+    }
+
+    #[test]
+    fn exact_division_by_zero_fails() {
+        assert!(div(&int(1), &int(0)).is_err());
+        assert!(parse_rational("1", "0").is_none());
+        assert!(matches!(
+            div(&Value::Number(1.0), &int(0)).unwrap(),
+            Value::Number(f64::INFINITY)
+        ));
+    }
+
+    #[test]
+    fn huge_exact_powers_fall_back_to_floats() {
+        assert_eq!(pow(&int(2), &int(10)).unwrap(), int(1024));
+        assert_eq!(pow(&int(-1), &int(2_000_000_001)).unwrap(), int(-1));
+        assert!(matches!(
+            pow(&int(10), &int(2_000_000_000)).unwrap(),
+            Value::Number(f64::INFINITY)
+        ));
+        assert!(matches!(
+            pow(&int(10), &int(-2_000_000_000)).unwrap(),
+            Value::Number(0.0)
+        ));
+        assert!(pow(&int(0), &int(-1)).is_err());
+    }
+}
@@ -1,25 +1,49 @@
 use std::{iter::Peekable, rc::Rc, str::Chars};
 
-use crate::{BadProgram, Result, Value};
+use im_rc::{OrdMap, OrdSet};
+
+use crate::{
+    BadProgram, Function, Result, Value,
+    builtins::BuiltinFn,
+    intern::intern,
+    keyword::Keyword,
+    number::{Int, parse_int, parse_rational},
+};
 
 #[derive(PartialEq, Debug)]
 enum Token {
     Open,
     Close,
+    OpenMap,
+    OpenSet,
+    CloseBrace,
+    Integer(Int),
+    Rational(String, String),
     Number(f64),
     Symbol(String),
+    Keyword(String),
+    Label(String),
     String(String),
+    Char(char),
+    Bytes(Vec<u8>),
+    Interpolated(Vec<Segment>),
     UnterminatedString,
     Unknown(char),
 }
 
+#[derive(PartialEq, Debug)]
+enum Segment {
+    Text(String),
+    Code(String),
+}
+
 struct Scanner<'a> {
     current_position: usize,
     it: Peekable<Chars<'a>>,
 }
 
 impl<'a> Scanner<'a> {
-    fn new(buf: &str) -> Scanner {
+    fn new(buf: &str) -> Scanner<'_> {
         Scanner {
             current_position: 0,
             it: buf.chars().peekable(),
@@ -44,9 +68,8 @@ impl<'a> Scanner<'a> {
         F: Fn(char) -> bool,
     {
         let mut it = self.it.clone();
-        match it.next() {
-            None => return false,
-            _ => (),
+        if it.next().is_none() {
+            return false;
         }
 
         if let Some(&ch) = it.peek() {
@@ -85,12 +108,14 @@ impl<'a> Scanner<'a> {
     }
 }
 
+const SYMBOL_CHARS: [char; 10] = ['$', '-', '+', '*', '/', '_', '<', '>', '=', '?'];
+
 struct Lexer<'a> {
     scanner: Scanner<'a>,
 }
 
 impl<'a> Lexer<'a> {
-    fn new(buf: &str) -> Lexer {
+    fn new(buf: &str) -> Lexer<'_> {
         Lexer {
             scanner: Scanner::new(buf),
         }
@@ -104,50 +129,268 @@ impl<'a> Lexer<'a> {
             '\r' => None,
             '[' => Some(Token::Open),
             ']' => Some(Token::Close),
-            x if x.is_numeric() => self.number(x),
-            x if x.is_ascii_alphabetic() || ['$', '-', '+', '*', '_'].contains(&x) => {
-                self.symbol(x)
+            '{' => Some(Token::OpenMap),
+            '}' => Some(Token::CloseBrace),
+            x if x.is_ascii_digit() => self.number(x),
+            '-' if self.scanner.peek().is_some_and(|c| c.is_ascii_digit()) => self.number('-'),
+            'b' if self.scanner.peek() == Some(&'"') => {
+                self.scanner.next();
+                self.bytes()
             }
-            '"' => {
-                let content: String = self
+            'f' if self.scanner.peek() == Some(&'"') => {
+                self.scanner.next();
+                self.interpolated()
+            }
+            x if x.is_ascii_alphabetic() || SYMBOL_CHARS.contains(&x) => self.symbol(x),
+            ':' => {
+                let name: String = self
                     .scanner
-                    .consume_while(|x| x != '"')
+                    .consume_while(|a| a.is_ascii_alphanumeric() || SYMBOL_CHARS.contains(&a))
                     .into_iter()
                     .collect();
 
-                if let Some(next) = self.scanner.next() {
-                    assert!(next == '"');
-
-                    Some(Token::String(content))
+                if name.is_empty() {
+                    Some(Token::Unknown(':'))
                 } else {
-                    Some(Token::UnterminatedString)
+                    Some(Token::Keyword(name))
                 }
             }
+            '"' => self.string(),
+            '\'' => self.char(),
+            '#' if self.scanner.peek() == Some(&'{') => {
+                self.scanner.next();
+                Some(Token::OpenSet)
+            }
             '#' => {
                 self.scanner.skip_while(|c| c != '\n');
-                return None;
+                None
             }
             c => Some(Token::Unknown(c)),
         }
     }
 
+    fn string(&mut self) -> Option<Token> {
+        let mut string = String::new();
+
+        loop {
+            match self.scanner.next() {
+                Some('"') => return Some(Token::String(string)),
+                None => return Some(Token::UnterminatedString),
+                Some('\\') => {
+                    if self.string_escape(&mut string).is_none() {
+                        return Some(Token::Unknown('\\'));
+                    }
+                }
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    // `f"Hello, {name}!"`. The code between the braces gets parsed on its own later. `{{` and `}}`
+    // are literal braces.
+    fn interpolated(&mut self) -> Option<Token> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+
+        loop {
+            match self.scanner.next() {
+                Some('"') => break,
+                None => return Some(Token::UnterminatedString),
+                Some('\\') => {
+                    if self.string_escape(&mut text).is_none() {
+                        return Some(Token::Unknown('\\'));
+                    }
+                }
+                Some('{') if self.scanner.peek() == Some(&'{') => {
+                    self.scanner.next();
+                    text.push('{');
+                }
+                Some('}') if self.scanner.peek() == Some(&'}') => {
+                    self.scanner.next();
+                    text.push('}');
+                }
+                Some('{') => {
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+
+                    match self.code() {
+                        Some(code) => segments.push(Segment::Code(code)),
+                        None => return Some(Token::UnterminatedString),
+                    }
+                }
+                Some('}') => return Some(Token::Unknown('}')),
+                Some(c) => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Some(Token::Interpolated(segments))
+    }
+
+    // Everything up to the closing brace. Braces of maps and sets inside it and anything in string
+    // or character literals doesn't count.
+    fn code(&mut self) -> Option<String> {
+        let mut code = String::new();
+        let mut depth = 0;
+
+        loop {
+            let c = self.scanner.next()?;
+            code.push(c);
+
+            match c {
+                '}' if depth == 0 => {
+                    code.pop();
+                    return Some(code);
+                }
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                '"' | '\'' => loop {
+                    let next = self.scanner.next()?;
+                    code.push(next);
+
+                    if next == '\\' {
+                        code.push(self.scanner.next()?);
+                    } else if next == c {
+                        break;
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn char(&mut self) -> Option<Token> {
+        let c = match self.scanner.next() {
+            Some('\\') => self.escape(),
+            Some('\'') | None => None,
+            c => c,
+        };
+
+        match (c, self.scanner.next()) {
+            (Some(c), Some('\'')) => Some(Token::Char(c)),
+            _ => Some(Token::Unknown('\'')),
+        }
+    }
+
+    // Anything that isn't ASCII gets encoded as UTF-8. `\xff` is a single byte.
+    fn bytes(&mut self) -> Option<Token> {
+        let mut bytes = Vec::new();
+
+        loop {
+            let c = match self.scanner.next() {
+                Some('"') => return Some(Token::Bytes(bytes)),
+                None => return Some(Token::UnterminatedString),
+                Some('\\') if self.scanner.peek() == Some(&'x') => {
+                    self.scanner.next();
+
+                    let hex: String = [self.scanner.next(), self.scanner.next()]
+                        .into_iter()
+                        .collect::<Option<_>>()
+                        .unwrap_or_default();
+
+                    match u8::from_str_radix(&hex, 16) {
+                        Ok(byte) => {
+                            bytes.push(byte);
+                            continue;
+                        }
+                        Err(_) => return Some(Token::Unknown('\\')),
+                    }
+                }
+                Some('\\') => match self.escape() {
+                    Some(c) => c,
+                    None => return Some(Token::Unknown('\\')),
+                },
+                Some(c) => c,
+            };
+
+            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+    }
+
+    // Called after a backslash. `\u{...}` takes the hexadecimal code point of any character.
+    fn escape(&mut self) -> Option<char> {
+        match self.scanner.next()? {
+            'u' => self.unicode(),
+            c => escaped(c),
+        }
+    }
+
+    // Strings didn't always have escapes, so a backslash that doesn't start one is just a
+    // backslash. Returns `None` for a `\u{...}` that isn't a valid character.
+    fn string_escape(&mut self, string: &mut String) -> Option<()> {
+        match self.scanner.peek().copied() {
+            Some('u') => {
+                self.scanner.next();
+
+                if self.scanner.peek() == Some(&'{') {
+                    string.push(self.unicode()?);
+                } else {
+                    string.push_str("\\u");
+                }
+            }
+            Some(c) if let Some(escaped) = escaped(c) => {
+                self.scanner.next();
+                string.push(escaped);
+            }
+            _ => string.push('\\'),
+        }
+
+        Some(())
+    }
+
+    // The `{...}` after `\u`.
+    fn unicode(&mut self) -> Option<char> {
+        if self.scanner.next()? != '{' {
+            return None;
+        }
+
+        let code: String = self
+            .scanner
+            .consume_while(|c| c.is_ascii_hexdigit())
+            .into_iter()
+            .collect();
+
+        if self.scanner.next()? != '}' {
+            return None;
+        }
+
+        char::from_u32(u32::from_str_radix(&code, 16).ok()?)
+    }
+
     fn number(&mut self, x: char) -> Option<Token> {
         let mut number = String::new();
         number.push(x);
         let num: String = self
             .scanner
-            .consume_while(|a| a.is_numeric())
+            .consume_while(|a| a.is_ascii_digit())
             .into_iter()
             .collect();
         number.push_str(num.as_str());
-        if self.scanner.peek() == Some(&'.') && self.scanner.consume_if_next(|ch| ch.is_numeric()) {
+        if self.scanner.peek() == Some(&'.')
+            && self.scanner.consume_if_next(|ch| ch.is_ascii_digit())
+        {
             let num2: String = self
                 .scanner
-                .consume_while(|a| a.is_numeric())
+                .consume_while(|a| a.is_ascii_digit())
                 .into_iter()
                 .collect();
             number.push('.');
             number.push_str(num2.as_str());
+        } else if self.scanner.peek() == Some(&'/')
+            && self.scanner.consume_if_next(|ch| ch.is_ascii_digit())
+        {
+            let denominator: String = self
+                .scanner
+                .consume_while(|a| a.is_ascii_digit())
+                .into_iter()
+                .collect();
+            return Some(Token::Rational(number, denominator));
+        } else {
+            return Some(Token::Integer(parse_int(&number).unwrap()));
         }
         Some(Token::Number(number.parse::<f64>().unwrap()))
     }
@@ -156,14 +399,34 @@ impl<'a> Lexer<'a> {
         let mut identifier: String = first.into();
         let rest: String = self
             .scanner
-            .consume_while(|a| a.is_ascii_alphanumeric() || ['$', '-', '+', '*', '_'].contains(&a))
+            .consume_while(|a| a.is_ascii_alphanumeric() || SYMBOL_CHARS.contains(&a))
             .into_iter()
             .collect();
         identifier.push_str(rest.as_str());
-        Some(Token::Symbol(identifier))
+
+        if self.scanner.peek() == Some(&':') {
+            self.scanner.next();
+            Some(Token::Label(identifier))
+        } else {
+            Some(Token::Symbol(identifier))
+        }
     }
 }
 
+// The character a backslash followed by `c` stands for.
+fn escaped(c: char) -> Option<char> {
+    Some(match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '\\' => '\\',
+        '\'' => '\'',
+        '"' => '"',
+        _ => return None,
+    })
+}
+
 fn tokenize(buf: &str) -> Vec<Token> {
     let mut lexer = Lexer::new(buf);
 
@@ -204,6 +467,18 @@ impl<'a> Parser<'a> {
 fn parse_node(parser: &mut Parser) -> Result<Value> {
     Ok(match parser.peek() {
         Some(Token::Open) => parse_list(parser)?,
+        Some(Token::OpenMap) => parse_map(parser)?,
+        Some(Token::OpenSet) => parse_set(parser)?,
+        Some(Token::Integer(n)) => {
+            let n = n.clone();
+            parser.advance();
+            Value::Int(n)
+        }
+        Some(Token::Rational(numerator, denominator)) => {
+            let n = parse_rational(numerator, denominator).ok_or(BadProgram)?;
+            parser.advance();
+            n
+        }
         Some(&Token::Number(n)) => {
             parser.advance();
             Value::Number(n)
@@ -213,11 +488,45 @@ fn parse_node(parser: &mut Parser) -> Result<Value> {
             parser.advance();
             Value::String(Rc::new(s))
         }
+        Some(Token::Bytes(bytes)) => {
+            let bytes = bytes.clone();
+            parser.advance();
+            Value::Bytes(Rc::new(bytes))
+        }
+        // Turns into a call that puts the pieces together. The call is to the builtin itself rather
+        // than its name, so that nothing else called `interpolate` can get in the way.
+        Some(Token::Interpolated(segments)) => {
+            let mut content = vec![Value::Fn(Function::Builtin(BuiltinFn::Interpolate))];
+
+            for segment in segments {
+                content.push(match segment {
+                    Segment::Text(text) => Value::String(Rc::new(text.clone())),
+                    Segment::Code(code) => parse(code)?,
+                });
+            }
+
+            parser.advance();
+            Value::List(content.into())
+        }
+        Some(&Token::Char(c)) => {
+            parser.advance();
+            Value::Char(c)
+        }
         Some(Token::Symbol(s)) => {
-            let s = s.clone().leak();
+            let s = intern(s);
             parser.advance();
             Value::Symbol(s)
         }
+        Some(Token::Keyword(s)) => {
+            let keyword = Keyword::new(s);
+            parser.advance();
+            Value::Keyword(keyword)
+        }
+        Some(Token::Label(s)) => {
+            let s = intern(s);
+            parser.advance();
+            Value::Label(s)
+        }
         _ => return Err(crate::BadProgram),
     })
 }
@@ -233,7 +542,50 @@ fn parse_list(parser: &mut Parser) -> Result<Value> {
 
     if parser.peek().is_some() {
         parser.advance();
-        Ok(Value::List(Rc::new(content)))
+        Ok(Value::List(content.into()))
+    } else {
+        Err(BadProgram)
+    }
+}
+
+fn parse_map(parser: &mut Parser) -> Result<Value> {
+    parser.advance();
+
+    let mut map = OrdMap::new();
+
+    while parser.peek().is_some_and(|t| t != &Token::CloseBrace) {
+        let key = parse_node(parser)?;
+
+        if parser.peek() == Some(&Token::CloseBrace) {
+            return Err(BadProgram);
+        }
+
+        // A key that is written twice would silently lose one of the values.
+        if map.insert(key, parse_node(parser)?).is_some() {
+            return Err(BadProgram);
+        }
+    }
+
+    if parser.peek().is_some() {
+        parser.advance();
+        Ok(Value::Map(map))
+    } else {
+        Err(BadProgram)
+    }
+}
+
+fn parse_set(parser: &mut Parser) -> Result<Value> {
+    parser.advance();
+
+    let mut set = OrdSet::new();
+
+    while parser.peek().is_some_and(|t| t != &Token::CloseBrace) {
+        set.insert(parse_node(parser)?);
+    }
+
+    if parser.peek().is_some() {
+        parser.advance();
+        Ok(Value::Set(set))
     } else {
         Err(BadProgram)
     }
@@ -247,3 +599,113 @@ pub fn parse(buf: &str) -> Result<Value> {
 
     parser.advance().is_none().then_some(node).ok_or(BadProgram)
 }
+
+// A whole sequence of values instead of a single one, like the statements in the prelude.
+pub fn parse_all(buf: &str) -> Result<Vec<Value>> {
+    let tokens = tokenize(buf);
+
+    let mut parser = Parser::new(&tokens);
+    let mut nodes = Vec::new();
+
+    while parser.peek().is_some() {
+        nodes.push(parse_node(&mut parser)?);
+    }
+
+    Ok(nodes)
+}
+
+// Parses data instead of code. The only difference is that the names of constants like `true` or
+// `nil` turn into their values, other symbols stay symbols.
+pub fn read(buf: &str) -> Result<Value> {
+    parse(buf).map(data)
+}
+
+fn data(value: Value) -> Value {
+    match value {
+        Value::Symbol("true") => Value::Bool(true),
+        Value::Symbol("false") => Value::Bool(false),
+        Value::Symbol("nil") => Value::Nil,
+        Value::Symbol("nan") => Value::Number(f64::NAN),
+        Value::Symbol("infinity") => Value::Number(f64::INFINITY),
+        Value::Symbol("-infinity") => Value::Number(f64::NEG_INFINITY),
+        Value::List(list) => Value::List(list.into_iter().map(data).collect()),
+        Value::Map(map) => Value::Map(
+            map.into_iter()
+                .map(|(key, value)| (data(key), data(value)))
+                .collect(),
+        ),
+        Value::Set(set) => Value::Set(set.into_iter().map(data).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn char_literals() {
+        assert_eq!(parse("'a'").unwrap(), Value::Char('a'));
+        assert_eq!(parse("'é'").unwrap(), Value::Char('é'));
+        assert_eq!(parse(r"'\n'").unwrap(), Value::Char('\n'));
+        assert_eq!(parse(r"'\''").unwrap(), Value::Char('\''));
+        assert_eq!(parse(r"'\u{1F600}'").unwrap(), Value::Char('😀'));
+    }
+
+    #[test]
+    fn invalid_char_literals() {
+        assert!(parse("''").is_err());
+        assert!(parse("'ab'").is_err());
+        assert!(parse("'a").is_err());
+        assert!(parse(r"'\u{D800}'").is_err());
+        assert!(parse(r"'\u{110000}'").is_err());
+    }
+
+    fn string(code: &str) -> String {
+        let Ok(Value::String(string)) = parse(code) else {
+            panic!("{code} isn't a string");
+        };
+
+        string.to_string()
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(string(r#""a\nb\t\"\\""#), "a\nb\t\"\\");
+        assert_eq!(string(r#""\u{e9}""#), "é");
+        assert!(parse(r#""\u{110000}""#).is_err());
+    }
+
+    #[test]
+    fn unknown_escapes_stay_as_they_are() {
+        assert_eq!(string(r#""C:\data""#), r"C:\data");
+        assert_eq!(string(r#""C:\users""#), r"C:\users");
+        assert_eq!(string(r#""\d+""#), r"\d+");
+    }
+
+    #[test]
+    fn symbols_are_interned() {
+        let (Ok(Value::Symbol(a)), Ok(Value::Symbol(b))) = (read("some-name"), read("some-name"))
+        else {
+            panic!("expected symbols");
+        };
+
+        assert!(std::ptr::eq(a, b));
+    }
+
+    #[test]
+    fn interpolated_strings_call_the_builtin() {
+        let Ok(Value::List(list)) = parse(r#"f"a {x} {{b}} {[+ 1 2]}""#) else {
+            panic!("expected a list");
+        };
+
+        assert!(matches!(
+            list.first(),
+            Some(Value::Fn(Function::Builtin(BuiltinFn::Interpolate)))
+        ));
+        assert_eq!(
+            Value::List(list.skip(1)),
+            parse(r#"["a " x " {b} " [+ 1 2]]"#).unwrap()
+        );
+    }
+}
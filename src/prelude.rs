@@ -0,0 +1,68 @@
+use std::rc::Rc;
+
+use crate::{Result, Scope, eval_statement, parser::parse_all};
+
+// Things that are easier to write in the language itself than as builtins. The prelude is a list of
+// block statements that build the scope every program starts out in.
+pub const SOURCE: &str = include_str!("prelude.lisp?");
+
+pub fn scope() -> Result<Rc<Scope>> {
+    let mut scope = Rc::new(Scope::Empty);
+
+    for statement in parse_all(SOURCE)? {
+        scope = eval_statement(&scope, &statement)?;
+    }
+
+    Ok(scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Value, number::tests::int, tests::run};
+
+    // Every definition gets called once, so one that refers to something that doesn't exist (or
+    // doesn't work the way it expects) shows up here.
+    #[test]
+    fn every_definition_works() {
+        let t = Value::Bool(true);
+        let f = Value::Bool(false);
+
+        assert_eq!(run("[not false]").unwrap(), t);
+        assert_eq!(run("[identity 1]").unwrap(), int(1));
+        assert_eq!(run("[[constantly 1] 2]").unwrap(), int(1));
+        assert_eq!(run("[[complement zero?] 0]").unwrap(), f);
+
+        assert_eq!(run("[inc 1]").unwrap(), int(2));
+        assert_eq!(run("[dec 1]").unwrap(), int(0));
+        assert_eq!(run("[zero? 0]").unwrap(), t);
+        assert_eq!(run("[pos? -1]").unwrap(), f);
+        assert_eq!(run("[neg? -1]").unwrap(), t);
+        assert_eq!(run("[even? -2]").unwrap(), t);
+        assert_eq!(run("[odd? -3]").unwrap(), t);
+
+        assert_eq!(run("[sum [list 1 2 3]]").unwrap(), int(6));
+        assert_eq!(run("[product [list 2 3 4]]").unwrap(), int(24));
+        assert_eq!(run("[second [list 1 2 3]]").unwrap(), int(2));
+        assert_eq!(run("[last [list 1 2 3]]").unwrap(), int(3));
+        assert_eq!(run("[last [list]]").unwrap(), Value::Nil);
+        assert_eq!(run("[count [list 1 2 3 4] even?]").unwrap(), int(2));
+        assert_eq!(run("[count [range 0 10] odd?]").unwrap(), int(5));
+
+        assert_eq!(
+            run(r#"[min_by [list "aa" "b" "ccc"] length]"#).unwrap(),
+            run(r#""b""#).unwrap()
+        );
+        assert_eq!(
+            run(r#"[max_by [list "aa" "b" "ccc"] length]"#).unwrap(),
+            run(r#""ccc""#).unwrap()
+        );
+        assert_eq!(
+            run("[frequencies [list :a :b :a]]").unwrap(),
+            run("{:a 2 :b 1}").unwrap()
+        );
+        assert_eq!(
+            run("[map_values {:a 1 :b 2} inc]").unwrap(),
+            run("{:a 2 :b 3}").unwrap()
+        );
+    }
+}
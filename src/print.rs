@@ -0,0 +1,129 @@
+use std::{
+    fmt::{Display, Formatter, Result, Write},
+    rc::Rc,
+};
+
+use chrono::SecondsFormat;
+
+use crate::{Value, time};
+
+// Prints values the way they would be written. Plain data like numbers, strings, lists and maps
+// reads back as an equal value. Records, variants, regexes, dates and durations print as the code
+// that makes them, which only gives them back when it's evaluated. Things like functions can't be
+// written at all and are printed in angle brackets.
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Value::Int(int) => write!(f, "{int}"),
+            Value::Rational(rational) => write!(f, "{rational}"),
+            Value::Number(float) => float_literal(f, *float),
+            Value::Bool(bool) => write!(f, "{bool}"),
+            Value::String(string) => {
+                f.write_char('"')?;
+
+                for c in string.chars() {
+                    escaped(f, c, '"')?;
+                }
+
+                f.write_char('"')
+            }
+            Value::Char(c) => {
+                f.write_char('\'')?;
+                escaped(f, *c, '\'')?;
+                f.write_char('\'')
+            }
+            Value::Bytes(bytes) => {
+                f.write_str("b\"")?;
+
+                for &byte in bytes.iter() {
+                    match byte {
+                        b'"' | b'\\' => write!(f, "\\{}", byte as char)?,
+                        b' '..=b'~' => f.write_char(byte as char)?,
+                        _ => write!(f, "\\x{byte:02x}")?,
+                    }
+                }
+
+                f.write_char('"')
+            }
+            Value::Symbol(name) => f.write_str(name),
+            Value::Keyword(keyword) => write!(f, ":{}", keyword.name()),
+            Value::Label(name) => write!(f, "{name}:"),
+            Value::List(list) => sequence(f, "[", list.iter(), "]"),
+            Value::Map(map) => sequence(
+                f,
+                "{",
+                map.iter().flat_map(|(key, value)| [key, value]),
+                "}",
+            ),
+            Value::Set(set) => sequence(f, "#{", set.iter(), "}"),
+            Value::Record(record) => write!(f, "{record}"),
+            Value::Variant(variant) => write!(f, "{variant}"),
+            Value::Regex(regex) => {
+                write!(
+                    f,
+                    "[regex {}]",
+                    Value::String(Rc::new(regex.as_str().to_string()))
+                )
+            }
+            Value::DateTime(datetime) => write!(
+                f,
+                "[datetime_parse \"{}\"]",
+                datetime.to_rfc3339_opts(SecondsFormat::AutoSi, false)
+            ),
+            Value::Duration(duration) => {
+                write!(f, "[duration seconds: {}]", time::seconds(duration))
+            }
+            Value::Fn(_) => f.write_str("<fn>"),
+            Value::Macro(_) => f.write_str("<macro>"),
+            Value::Io(_) => f.write_str("<io>"),
+            Value::Thunk(_) => f.write_str("<thunk>"),
+            Value::Seq(_) => f.write_str("<seq>"),
+            Value::Nil => f.write_str("nil"),
+        }
+    }
+}
+
+// Floats always get a decimal point, so they don't turn into integers when they're read back.
+fn float_literal(f: &mut Formatter<'_>, float: f64) -> Result {
+    if float.is_nan() {
+        f.write_str("nan")
+    } else if float.is_infinite() {
+        f.write_str(if float > 0.0 { "infinity" } else { "-infinity" })
+    } else if float.fract() == 0.0 {
+        write!(f, "{float}.0")
+    } else {
+        write!(f, "{float}")
+    }
+}
+
+fn escaped(f: &mut Formatter<'_>, c: char, quote: char) -> Result {
+    match c {
+        '\n' => f.write_str("\\n"),
+        '\t' => f.write_str("\\t"),
+        '\r' => f.write_str("\\r"),
+        '\0' => f.write_str("\\0"),
+        '\\' => f.write_str("\\\\"),
+        c if c == quote => write!(f, "\\{c}"),
+        c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32),
+        c => f.write_char(c),
+    }
+}
+
+fn sequence<'a>(
+    f: &mut Formatter<'_>,
+    open: &str,
+    values: impl Iterator<Item = &'a Value>,
+    close: &str,
+) -> Result {
+    f.write_str(open)?;
+
+    for (i, value) in values.enumerate() {
+        if i > 0 {
+            f.write_char(' ')?;
+        }
+
+        write!(f, "{value}")?;
+    }
+
+    f.write_str(close)
+}
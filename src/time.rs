@@ -0,0 +1,250 @@
+use std::fmt::Write;
+
+use chrono::{
+    DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, TimeDelta, Timelike,
+    Weekday,
+    format::{Item, StrftimeItems},
+};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, ToPrimitive};
+
+use crate::{
+    BadProgram, Result, Value,
+    keyword::Keyword,
+    number::{self, Int, Rounding},
+};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+// The units `duration` accepts, in nanoseconds.
+pub const UNITS: &[(&str, i64)] = &[
+    ("days", 86_400 * NANOS_PER_SECOND),
+    ("hours", 3_600 * NANOS_PER_SECOND),
+    ("minutes", 60 * NANOS_PER_SECOND),
+    ("seconds", NANOS_PER_SECOND),
+    ("milliseconds", 1_000_000),
+];
+
+// Without a format this reads RFC 3339 (the usual ISO 8601 timestamps), or just a date and time or
+// a date without an offset, which are then taken to be UTC. With a format the same goes for the
+// pattern, which uses strftime's `%` codes.
+pub fn parse(text: &str, format: Option<&str>) -> Option<DateTime<FixedOffset>> {
+    let utc = |naive: NaiveDateTime| naive.and_utc().fixed_offset();
+
+    match format {
+        None => DateTime::parse_from_rfc3339(text)
+            .ok()
+            .or_else(|| Some(utc(text.parse::<NaiveDateTime>().ok()?)))
+            .or_else(|| Some(utc(text.parse::<NaiveDate>().ok()?.and_hms_opt(0, 0, 0)?))),
+        Some(format) => DateTime::parse_from_str(text, format)
+            .ok()
+            .or_else(|| Some(utc(NaiveDateTime::parse_from_str(text, format).ok()?)))
+            .or_else(|| {
+                Some(utc(NaiveDate::parse_from_str(text, format)
+                    .ok()?
+                    .and_hms_opt(0, 0, 0)?))
+            }),
+    }
+}
+
+// RFC 3339 without a format. chrono panics on formats it doesn't understand, so those get checked
+// first.
+pub fn format(datetime: &DateTime<FixedOffset>, format: Option<&str>) -> Result<String> {
+    let Some(format) = format else {
+        return Ok(datetime.to_rfc3339_opts(SecondsFormat::AutoSi, false));
+    };
+
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+
+    if items.contains(&Item::Error) {
+        return Err(BadProgram);
+    }
+
+    let mut output = String::new();
+    write!(output, "{}", datetime.format_with_items(items.into_iter())).map_err(|_| BadProgram)?;
+
+    Ok(output)
+}
+
+pub fn fields(datetime: &DateTime<FixedOffset>) -> Value {
+    let int = |value: u32| Value::Int(Int::Small(value.into()));
+
+    Value::Map(
+        [
+            ("year", Value::Int(Int::Small(datetime.year().into()))),
+            ("month", int(datetime.month())),
+            ("day", int(datetime.day())),
+            ("hour", int(datetime.hour())),
+            ("minute", int(datetime.minute())),
+            ("second", int(datetime.second())),
+            ("nanosecond", int(datetime.nanosecond())),
+            (
+                "weekday",
+                Value::Keyword(Keyword::new(weekday(datetime.weekday()))),
+            ),
+            ("offset", offset_duration(datetime.offset())),
+        ]
+        .into_iter()
+        .map(|(name, value)| (Value::Keyword(Keyword::new(name)), value))
+        .collect(),
+    )
+}
+
+fn weekday(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+fn offset_duration(offset: &FixedOffset) -> Value {
+    Value::Duration(TimeDelta::seconds(offset.local_minus_utc().into()))
+}
+
+// Offsets are durations east of UTC and have to be less than a day.
+pub fn offset(duration: &TimeDelta) -> Result<FixedOffset> {
+    if duration.subsec_nanos() != 0 {
+        return Err(BadProgram);
+    }
+
+    duration
+        .num_seconds()
+        .to_i32()
+        .and_then(FixedOffset::east_opt)
+        .ok_or(BadProgram)
+}
+
+// The sum of all the amounts, rounded to the nearest nanosecond.
+pub fn duration<'a>(amounts: impl Iterator<Item = (&'a Value, i64)>) -> Result<TimeDelta> {
+    let mut nanos = Value::Int(Int::Small(0));
+
+    for (amount, unit) in amounts {
+        nanos = number::add(&nanos, &number::mul(amount, &Value::Int(Int::Small(unit)))?)?;
+    }
+
+    let nanos = match number::round(&nanos, Rounding::Round)? {
+        Value::Int(int) => int.to_big(),
+        Value::Number(float) => BigInt::from_f64(float).ok_or(BadProgram)?,
+        _ => unreachable!(),
+    };
+
+    let (seconds, nanos) = (
+        &nanos / NANOS_PER_SECOND,
+        (&nanos % NANOS_PER_SECOND).to_i64().unwrap_or(0),
+    );
+
+    // `TimeDelta::new` wants the nanoseconds to be positive.
+    let (seconds, nanos) = if nanos < 0 {
+        (seconds - 1, nanos + NANOS_PER_SECOND)
+    } else {
+        (seconds, nanos)
+    };
+
+    seconds
+        .to_i64()
+        .and_then(|seconds| TimeDelta::new(seconds, nanos as u32))
+        .ok_or(BadProgram)
+}
+
+// Exact, so a rational if it isn't a whole number of seconds.
+pub fn seconds(duration: &TimeDelta) -> Value {
+    let nanos = BigInt::from(duration.num_seconds()) * NANOS_PER_SECOND
+        + BigInt::from(duration.subsec_nanos());
+
+    number::rational(BigRational::new(nanos, NANOS_PER_SECOND.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numerator: i64, denominator: i64) -> Value {
+        number::rational(BigRational::new(numerator.into(), denominator.into()))
+    }
+
+    fn seconds_of(amount: Value) -> TimeDelta {
+        duration([(&amount, NANOS_PER_SECOND)].into_iter()).unwrap()
+    }
+
+    #[test]
+    fn durations_round_to_nanoseconds() {
+        assert_eq!(seconds_of(ratio(3, 2)), TimeDelta::milliseconds(1500));
+        assert_eq!(seconds_of(ratio(-3, 2)), TimeDelta::milliseconds(-1500));
+        assert_eq!(seconds_of(ratio(1, 3)), TimeDelta::nanoseconds(333_333_333));
+        assert_eq!(
+            seconds_of(ratio(-1, 3)),
+            TimeDelta::nanoseconds(-333_333_333)
+        );
+        assert_eq!(
+            seconds_of(ratio(-2, 3)),
+            TimeDelta::nanoseconds(-666_666_667)
+        );
+        assert_eq!(
+            seconds_of(Value::Number(-0.25)),
+            TimeDelta::milliseconds(-250)
+        );
+    }
+
+    #[test]
+    fn durations_add_up_all_units() {
+        let (one, two) = (Value::Int(Int::Small(1)), Value::Int(Int::Small(2)));
+
+        assert_eq!(
+            duration([(&one, UNITS[1].1), (&two, UNITS[2].1)].into_iter()).unwrap(),
+            TimeDelta::minutes(62)
+        );
+        assert!(duration([(&Value::Number(f64::NAN), 1)].into_iter()).is_err());
+    }
+
+    #[test]
+    fn seconds_are_exact() {
+        assert_eq!(seconds(&TimeDelta::milliseconds(-1500)), ratio(-3, 2));
+        assert_eq!(seconds(&TimeDelta::seconds(3)), ratio(3, 1));
+        assert_eq!(
+            seconds(&seconds_of(ratio(-1, 3))),
+            ratio(-333_333_333, NANOS_PER_SECOND)
+        );
+    }
+
+    #[test]
+    fn parsing_and_formatting() {
+        let datetime = parse("2024-05-06T12:30:00+02:00", None).unwrap();
+
+        assert_eq!(
+            format(&datetime, None).unwrap(),
+            "2024-05-06T12:30:00+02:00"
+        );
+        assert_eq!(format(&datetime, Some("%d.%m.%Y")).unwrap(), "06.05.2024");
+        assert!(format(&datetime, Some("%Q")).is_err());
+
+        let date = parse("2024-05-06", None).unwrap();
+
+        assert_eq!(format(&date, None).unwrap(), "2024-05-06T00:00:00+00:00");
+        assert_eq!(parse("06.05.2024", Some("%d.%m.%Y")).unwrap(), date);
+        assert_eq!(parse("yesterday", None), None);
+    }
+
+    #[test]
+    fn the_same_moment_is_equal_regardless_of_the_offset() {
+        let a = parse("2024-05-06T12:30:00+02:00", None).unwrap();
+        let b = parse("2024-05-06T10:30:00Z", None).unwrap();
+
+        assert_eq!(Value::DateTime(a), Value::DateTime(b));
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(
+            offset(&TimeDelta::hours(-5)).unwrap().local_minus_utc(),
+            -5 * 3600
+        );
+        assert!(offset(&TimeDelta::milliseconds(1)).is_err());
+        assert!(offset(&TimeDelta::days(1)).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use std::rc::Rc;
+
+use crate::{BadProgram, Function, Result, Scope, Value, intern::intern};
+
+#[derive(Debug)]
+pub struct RecordType {
+    name: &'static str,
+    fields: Vec<&'static str>,
+}
+
+impl RecordType {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+pub struct Record {
+    ty: Rc<RecordType>,
+    values: Vec<Value>,
+}
+
+impl Record {
+    pub fn ty(&self) -> &Rc<RecordType> {
+        &self.ty
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+}
+
+impl std::fmt::Debug for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut record = f.debug_struct(self.ty.name);
+
+        for (field, value) in self.ty.fields.iter().zip(&self.values) {
+            record.field(field, value);
+        }
+
+        record.finish()
+    }
+}
+
+// Printed as the constructor call that creates it, like `[Point 1 2]`.
+impl std::fmt::Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}", self.ty.name)?;
+
+        for value in &self.values {
+            write!(f, " {value}")?;
+        }
+
+        f.write_str("]")
+    }
+}
+
+#[derive(Debug)]
+pub struct SumType {
+    name: &'static str,
+    // The names of the variants and their fields.
+    variants: Vec<(&'static str, Vec<&'static str>)>,
+}
+
+impl SumType {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+pub struct Variant {
+    ty: Rc<SumType>,
+    tag: usize,
+    payload: Vec<Value>,
+}
+
+impl Variant {
+    pub fn ty(&self) -> &Rc<SumType> {
+        &self.ty
+    }
+
+    pub fn tag(&self) -> usize {
+        self.tag
+    }
+
+    pub fn payload(&self) -> &[Value] {
+        &self.payload
+    }
+}
+
+impl std::fmt::Debug for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, fields) = &self.ty.variants[self.tag];
+
+        let name = format!("{}::{}", self.ty.name, name);
+        let mut variant = f.debug_struct(&name);
+
+        for (field, value) in fields.iter().zip(&self.payload) {
+            variant.field(field, value);
+        }
+
+        variant.finish()
+    }
+}
+
+// Like records, but variants without fields are just their name.
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, _) = &self.ty.variants[self.tag];
+
+        if self.payload.is_empty() {
+            return f.write_str(name);
+        }
+
+        write!(f, "[{name}")?;
+
+        for value in &self.payload {
+            write!(f, " {value}")?;
+        }
+
+        f.write_str("]")
+    }
+}
+
+fn field_names(fields: &[Value]) -> Result<Vec<&'static str>> {
+    let mut names = Vec::with_capacity(fields.len());
+
+    for field in fields {
+        match *field {
+            Value::Symbol(field) if !names.contains(&field) => names.push(field),
+            _ => return Err(BadProgram),
+        }
+    }
+
+    Ok(names)
+}
+
+// `[record Point [x y]]` defines `Point` to construct a record, `Point?` to check whether something
+// is one and `Point-x` and `Point-y` to get the fields. Two record types with the same name are
+// still different types.
+pub fn define_record(scope: &Rc<Scope>, name: &'static str, fields: &[Value]) -> Result<Rc<Scope>> {
+    let ty = Rc::new(RecordType {
+        name,
+        fields: field_names(fields)?,
+    });
+
+    let mut scope = scope.clone().with(
+        name,
+        Value::Fn(Function::native(Some(ty.fields.len()), {
+            let ty = ty.clone();
+            move |params| {
+                if params.len() != ty.fields.len() {
+                    return Err(BadProgram);
+                }
+
+                Ok(Value::Record(Rc::new(Record {
+                    ty: ty.clone(),
+                    values: params.collect::<Result<_>>()?,
+                })))
+            }
+        })),
+    );
+
+    scope = scope.with(
+        intern(&format!("{name}?")),
+        Value::Fn(Function::native(Some(1), {
+            let ty = ty.clone();
+            move |params| {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Bool(
+                    matches!(value?, Value::Record(record) if Rc::ptr_eq(&record.ty, &ty)),
+                ))
+            }
+        })),
+    );
+
+    for (i, field) in ty.fields.iter().enumerate() {
+        scope = scope.with(
+            intern(&format!("{name}-{field}")),
+            Value::Fn(Function::native(Some(1), {
+                let ty = ty.clone();
+                move |params| {
+                    let (Some(Ok(Value::Record(record))), None) = (params.next(), params.next())
+                    else {
+                        return Err(BadProgram);
+                    };
+
+                    if !Rc::ptr_eq(&record.ty, &ty) {
+                        return Err(BadProgram);
+                    }
+
+                    Ok(record.values[i].clone())
+                }
+            })),
+        );
+    }
+
+    Ok(scope)
+}
+
+// `[type Shape [Circle r] [Rect w h]]` defines the constructors `Circle` and `Rect`, the predicates
+// `Shape?`, `Circle?` and `Rect?` and accessors for the fields of each variant like `Rect-w`.
+// Variants without any fields aren't constructed, `[type Option [None] [Some x]]` just defines
+// `None` to be the value itself.
+pub fn define_type(scope: &Rc<Scope>, name: &'static str, variants: &[Value]) -> Result<Rc<Scope>> {
+    let mut definitions: Vec<(&'static str, Vec<&'static str>)> =
+        Vec::with_capacity(variants.len());
+
+    for variant in variants {
+        let Value::List(variant) = variant else {
+            return Err(BadProgram);
+        };
+
+        let variant = variant.as_slice();
+
+        let Some((&Value::Symbol(variant_name), fields)) = variant.split_first() else {
+            return Err(BadProgram);
+        };
+
+        if definitions.iter().any(|&(other, _)| other == variant_name) {
+            return Err(BadProgram);
+        }
+
+        definitions.push((variant_name, field_names(fields)?));
+    }
+
+    let ty = Rc::new(SumType {
+        name,
+        variants: definitions,
+    });
+
+    let mut scope = scope.clone().with(
+        intern(&format!("{name}?")),
+        Value::Fn(Function::native(Some(1), {
+            let ty = ty.clone();
+            move |params| {
+                let (Some(value), None) = (params.next(), params.next()) else {
+                    return Err(BadProgram);
+                };
+
+                Ok(Value::Bool(
+                    matches!(value?, Value::Variant(variant) if Rc::ptr_eq(&variant.ty, &ty)),
+                ))
+            }
+        })),
+    );
+
+    for (tag, (variant_name, fields)) in ty.variants.iter().enumerate() {
+        let constructor = if fields.is_empty() {
+            Value::Variant(Rc::new(Variant {
+                ty: ty.clone(),
+                tag,
+                payload: Vec::new(),
+            }))
+        } else {
+            Value::Fn(Function::native(Some(fields.len()), {
+                let ty = ty.clone();
+                move |params| {
+                    if params.len() != ty.variants[tag].1.len() {
+                        return Err(BadProgram);
+                    }
+
+                    Ok(Value::Variant(Rc::new(Variant {
+                        ty: ty.clone(),
+                        tag,
+                        payload: params.collect::<Result<_>>()?,
+                    })))
+                }
+            }))
+        };
+
+        scope = scope.with(variant_name, constructor);
+
+        scope = scope.with(
+            intern(&format!("{variant_name}?")),
+            Value::Fn(Function::native(Some(1), {
+                let ty = ty.clone();
+                move |params| {
+                    let (Some(value), None) = (params.next(), params.next()) else {
+                        return Err(BadProgram);
+                    };
+
+                    Ok(Value::Bool(matches!(
+                        value?,
+                        Value::Variant(variant)
+                            if Rc::ptr_eq(&variant.ty, &ty) && variant.tag == tag
+                    )))
+                }
+            })),
+        );
+
+        for (i, field) in fields.iter().enumerate() {
+            scope = scope.with(
+                intern(&format!("{variant_name}-{field}")),
+                Value::Fn(Function::native(Some(1), {
+                    let ty = ty.clone();
+                    move |params| {
+                        let (Some(Ok(Value::Variant(variant))), None) =
+                            (params.next(), params.next())
+                        else {
+                            return Err(BadProgram);
+                        };
+
+                        if !Rc::ptr_eq(&variant.ty, &ty) || variant.tag != tag {
+                            return Err(BadProgram);
+                        }
+
+                        Ok(variant.payload[i].clone())
+                    }
+                })),
+            );
+        }
+    }
+
+    Ok(scope)
+}